@@ -0,0 +1,70 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use jet::ops::*;
+use jet::{Context, Expression, Program};
+
+fn flag_rule(plan: &str, min_age: i64) -> Box<dyn Expression> {
+    and([
+        eq(get("plan"), str(plan)),
+        or([
+            gt(get("age"), int(min_age)),
+            eq(get("roles"), str_array(["admin", "dev"])),
+        ]),
+        not(get("banned")),
+        eq(get("country"), str("DE")),
+    ])
+}
+
+fn context() -> Context {
+    Context::new()
+        .set_str("plan", "pro")
+        .set_int("age", 21)
+        .set_str_array("roles", ["dev".to_string(), "qa".to_string()])
+        .set_bool("banned", false)
+        .set_str("country", "DE")
+}
+
+fn bench_flag_rule(c: &mut Criterion) {
+    let expression = flag_rule("pro", 17);
+    let program = expression.compile();
+    let context = context();
+
+    let mut group = c.benchmark_group("flag_rule");
+    group.bench_function("tree", |b| {
+        b.iter(|| expression.eval(black_box(&context)).unwrap())
+    });
+    group.bench_function("program", |b| {
+        b.iter(|| program.eval(black_box(&context)).unwrap())
+    });
+    group.finish();
+}
+
+/// A hundred rules against one context, as when a request checks every flag.
+fn bench_flag_rules(c: &mut Criterion) {
+    let expressions: Vec<Box<dyn Expression>> = (0..100)
+        .map(|position| flag_rule(["free", "pro"][position % 2], position as i64 % 30))
+        .collect();
+    let programs = Program::compile_all(expressions.iter().map(|expression| expression.as_ref()));
+    let context = context();
+
+    let mut group = c.benchmark_group("flag_rules");
+    group.bench_function("tree", |b| {
+        b.iter(|| {
+            for expression in &expressions {
+                black_box(expression.eval(black_box(&context)).unwrap());
+            }
+        })
+    });
+    group.bench_function("program", |b| {
+        b.iter(|| {
+            let frame = programs[0].frame(black_box(&context));
+
+            for program in &programs {
+                black_box(program.eval_in(&frame).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_flag_rule, bench_flag_rules);
+criterion_main!(benches);
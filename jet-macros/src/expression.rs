@@ -0,0 +1,435 @@
+//! Parser for the `jet!` macro. The grammar, from lowest to highest precedence:
+//!
+//! ```text
+//! expr       := and ("||" and)*
+//! and        := comparison ("&&" comparison)*
+//! comparison := unary (("==" | "!=" | ">" | ">=" | "<" | "<=") unary)?
+//! unary      := "!" unary | operand
+//! operand    := literal | "-" number | "[" literals "]" | path | "get" "(" string ")" | "(" expr ")"
+//! path       := ident ("." ident)*
+//! ```
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{bracketed, parenthesized, Ident, Lit, LitStr, Result, Token};
+
+pub enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+    Not(Box<Expr>),
+    Eq(Box<Expr>, Box<Expr>),
+    Gt(Box<Expr>, Box<Expr>),
+    Get(String),
+    Literal(Literal),
+}
+
+pub enum Literal {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    BoolArray(Vec<bool>),
+    IntArray(Vec<i64>),
+    FloatArray(Vec<f64>),
+    StrArray(Vec<String>),
+}
+
+impl Parse for Expr {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let expr = parse_or(input)?;
+
+        if !input.is_empty() {
+            return Err(input.error("expected `&&`, `||` or end of expression"));
+        }
+
+        Ok(expr)
+    }
+}
+
+fn parse_or(input: ParseStream) -> Result<Expr> {
+    let mut args = vec![parse_and(input)?];
+
+    while input.peek(Token![||]) {
+        input.parse::<Token![||]>()?;
+        args.push(parse_and(input)?);
+    }
+
+    Ok(if args.len() == 1 {
+        args.remove(0)
+    } else {
+        Expr::Or(args)
+    })
+}
+
+fn parse_and(input: ParseStream) -> Result<Expr> {
+    let mut args = vec![parse_comparison(input)?];
+
+    while input.peek(Token![&&]) {
+        input.parse::<Token![&&]>()?;
+        args.push(parse_comparison(input)?);
+    }
+
+    Ok(if args.len() == 1 {
+        args.remove(0)
+    } else {
+        Expr::And(args)
+    })
+}
+
+fn parse_comparison(input: ParseStream) -> Result<Expr> {
+    let left = parse_unary(input)?;
+
+    if input.peek(Token![==]) {
+        input.parse::<Token![==]>()?;
+        let right = parse_unary(input)?;
+        Ok(Expr::Eq(Box::new(left), Box::new(right)))
+    } else if input.peek(Token![!=]) {
+        input.parse::<Token![!=]>()?;
+        let right = parse_unary(input)?;
+        Ok(Expr::Not(Box::new(Expr::Eq(
+            Box::new(left),
+            Box::new(right),
+        ))))
+    } else if input.peek(Token![>=]) {
+        let operator = input.parse::<Token![>=]>()?;
+        let right = parse_unary(input)?;
+        greater_or_equal(left, right, operator.spans[0])
+    } else if input.peek(Token![<=]) {
+        let operator = input.parse::<Token![<=]>()?;
+        let right = parse_unary(input)?;
+        greater_or_equal(right, left, operator.spans[0])
+    } else if input.peek(Token![>]) {
+        input.parse::<Token![>]>()?;
+        let right = parse_unary(input)?;
+        Ok(Expr::Gt(Box::new(left), Box::new(right)))
+    } else if input.peek(Token![<]) {
+        input.parse::<Token![<]>()?;
+        let right = parse_unary(input)?;
+        Ok(Expr::Gt(Box::new(right), Box::new(left)))
+    } else {
+        Ok(left)
+    }
+}
+
+fn parse_unary(input: ParseStream) -> Result<Expr> {
+    if input.peek(Token![!]) && !input.peek(Token![!=]) {
+        input.parse::<Token![!]>()?;
+        return Ok(Expr::Not(Box::new(parse_unary(input)?)));
+    }
+
+    parse_operand(input)
+}
+
+/// `left >= right` without evaluating either side twice. It is `!(right > left)` unless
+/// both sides may be floats, since NaN is neither greater than, equal to nor less than
+/// anything. Only context values and float literals may be floats, and a context value
+/// compared with a float literal `c` is compared with the float just below `c` instead.
+fn greater_or_equal(left: Expr, right: Expr, span: Span) -> Result<Expr> {
+    match (left, right) {
+        (left @ Expr::Get(_), Expr::Literal(Literal::Float(value))) => Ok(Expr::Gt(
+            Box::new(left),
+            Box::new(Expr::Literal(Literal::Float(next_down(value)))),
+        )),
+        (Expr::Literal(Literal::Float(value)), right @ Expr::Get(_)) => Ok(Expr::Gt(
+            Box::new(Expr::Literal(Literal::Float(next_up(value)))),
+            Box::new(right),
+        )),
+        (Expr::Get(_), Expr::Get(_) | Expr::Literal(Literal::FloatArray(_)))
+        | (Expr::Literal(Literal::FloatArray(_)), Expr::Get(_)) => Err(syn::Error::new(
+            span,
+            "`>=` and `<=` cannot compare a context value with another context value or a \
+             float array, write `a > b || a == b` instead",
+        )),
+        (left, right) => Ok(Expr::Not(Box::new(Expr::Gt(
+            Box::new(right),
+            Box::new(left),
+        )))),
+    }
+}
+
+/// Smallest float greater than `value`, which is finite since it comes from a literal.
+fn next_up(value: f64) -> f64 {
+    if value == 0.0 {
+        f64::from_bits(1)
+    } else if value > 0.0 {
+        f64::from_bits(value.to_bits() + 1)
+    } else {
+        f64::from_bits(value.to_bits() - 1)
+    }
+}
+
+/// Largest float less than `value`.
+fn next_down(value: f64) -> f64 {
+    -next_up(-value)
+}
+
+fn parse_operand(input: ParseStream) -> Result<Expr> {
+    if input.peek(syn::token::Paren) {
+        let content;
+        parenthesized!(content in input);
+        let expr = parse_or(&content)?;
+
+        if !content.is_empty() {
+            return Err(content.error("expected `)`"));
+        }
+
+        return Ok(expr);
+    }
+
+    if input.peek(syn::token::Bracket) {
+        let content;
+        let bracket = bracketed!(content in input);
+        return parse_array(&content, bracket.span.join()).map(Expr::Literal);
+    }
+
+    if input.peek(Lit) || input.peek(Token![-]) {
+        return parse_literal(input).map(Expr::Literal);
+    }
+
+    if input.peek(Ident) && input.peek2(syn::token::Paren) {
+        let ident: Ident = input.parse()?;
+
+        if ident != "get" {
+            return Err(syn::Error::new(
+                ident.span(),
+                "unknown function, expected `get`",
+            ));
+        }
+
+        let content;
+        parenthesized!(content in input);
+        let name: LitStr = content.parse()?;
+
+        if !content.is_empty() {
+            return Err(content.error("`get` takes a single string argument"));
+        }
+
+        return Ok(Expr::Get(name.value()));
+    }
+
+    if input.peek(Ident) {
+        let mut path = input.parse::<Ident>()?.to_string();
+
+        while input.peek(Token![.]) {
+            input.parse::<Token![.]>()?;
+            path.push('.');
+            path.push_str(&input.parse::<Ident>()?.to_string());
+        }
+
+        return Ok(Expr::Get(path));
+    }
+
+    Err(input.error("expected a literal, a context name or `(`"))
+}
+
+fn parse_literal(input: ParseStream) -> Result<Literal> {
+    let negative = if input.peek(Token![-]) {
+        input.parse::<Token![-]>()?;
+        true
+    } else {
+        false
+    };
+
+    let lit: Lit = input.parse()?;
+
+    match lit {
+        Lit::Int(int) => {
+            let value = if negative {
+                format!("-{}", int.base10_digits())
+                    .parse::<i64>()
+                    .map_err(|_| syn::Error::new(int.span(), "integer does not fit i64"))?
+            } else {
+                int.base10_parse::<i64>()?
+            };
+
+            Ok(Literal::Int(value))
+        }
+        Lit::Float(float) => {
+            let value = float.base10_parse::<f64>()?;
+            Ok(Literal::Float(if negative { -value } else { value }))
+        }
+        Lit::Str(content) if !negative => Ok(Literal::Str(content.value())),
+        Lit::Bool(content) if !negative => Ok(Literal::Bool(content.value)),
+        lit => Err(syn::Error::new(lit.span(), "unsupported literal")),
+    }
+}
+
+fn parse_array(input: ParseStream, span: Span) -> Result<Literal> {
+    let mut items = Vec::new();
+
+    while !input.is_empty() {
+        items.push(parse_literal(input)?);
+
+        if !input.is_empty() {
+            input.parse::<Token![,]>()?;
+        }
+    }
+
+    let mixed = || syn::Error::new(span, "array items must have the same type");
+
+    match items.first() {
+        None => Err(syn::Error::new(span, "arrays must not be empty")),
+        Some(Literal::Bool(_)) => items
+            .into_iter()
+            .map(|item| match item {
+                Literal::Bool(value) => Ok(value),
+                _ => Err(mixed()),
+            })
+            .collect::<Result<_>>()
+            .map(Literal::BoolArray),
+        Some(Literal::Int(_)) => items
+            .into_iter()
+            .map(|item| match item {
+                Literal::Int(value) => Ok(value),
+                _ => Err(mixed()),
+            })
+            .collect::<Result<_>>()
+            .map(Literal::IntArray),
+        Some(Literal::Float(_)) => items
+            .into_iter()
+            .map(|item| match item {
+                Literal::Float(value) => Ok(value),
+                _ => Err(mixed()),
+            })
+            .collect::<Result<_>>()
+            .map(Literal::FloatArray),
+        Some(Literal::Str(_)) => items
+            .into_iter()
+            .map(|item| match item {
+                Literal::Str(value) => Ok(value),
+                _ => Err(mixed()),
+            })
+            .collect::<Result<_>>()
+            .map(Literal::StrArray),
+        Some(_) => Err(syn::Error::new(span, "arrays must not be nested")),
+    }
+}
+
+impl Expr {
+    pub fn expand(&self) -> TokenStream {
+        match self {
+            Expr::Or(args) => {
+                let args = args.iter().map(Expr::expand);
+                quote! { ::jet::ops::or(::std::vec![#(#args),*]) }
+            }
+            Expr::And(args) => {
+                let args = args.iter().map(Expr::expand);
+                quote! { ::jet::ops::and(::std::vec![#(#args),*]) }
+            }
+            Expr::Not(arg) => {
+                let arg = arg.expand();
+                quote! { ::jet::ops::not(#arg) }
+            }
+            Expr::Eq(left, right) => {
+                let (left, right) = (left.expand(), right.expand());
+                quote! { ::jet::ops::eq(#left, #right) }
+            }
+            Expr::Gt(left, right) => {
+                let (left, right) = (left.expand(), right.expand());
+                quote! { ::jet::ops::gt(#left, #right) }
+            }
+            Expr::Get(name) => quote! { ::jet::ops::get(#name) },
+            Expr::Literal(literal) => literal.expand(),
+        }
+    }
+}
+
+impl Literal {
+    fn expand(&self) -> TokenStream {
+        match self {
+            Literal::Bool(value) => quote! { ::jet::ops::bool(#value) },
+            Literal::Int(value) => quote! { ::jet::ops::int(#value) },
+            Literal::Float(value) => quote! { ::jet::ops::float(#value) },
+            Literal::Str(value) => quote! { ::jet::ops::str(#value) },
+            Literal::BoolArray(values) => quote! { ::jet::ops::bool_array([#(#values),*]) },
+            Literal::IntArray(values) => quote! { ::jet::ops::int_array([#(#values),*]) },
+            Literal::FloatArray(values) => quote! { ::jet::ops::float_array([#(#values),*]) },
+            Literal::StrArray(values) => quote! { ::jet::ops::str_array([#(#values),*]) },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(input: &str) -> Result<String> {
+        syn::parse_str::<Expr>(input).map(|expr| expr.expand().to_string())
+    }
+
+    fn assert_expands(input: &str, expected: TokenStream) {
+        assert_eq!(expand(input).unwrap(), expected.to_string());
+    }
+
+    #[test]
+    fn it_expands_to_builder_calls() {
+        assert_expands(
+            "userId == 1 && age > 18",
+            quote! {
+                ::jet::ops::and(::std::vec![
+                    ::jet::ops::eq(::jet::ops::get("userId"), ::jet::ops::int(1i64)),
+                    ::jet::ops::gt(::jet::ops::get("age"), ::jet::ops::int(18i64))
+                ])
+            },
+        );
+        assert_expands(
+            "!(user.country != \"DE\") || get(\"plan-name\") < -1.5",
+            quote! {
+                ::jet::ops::or(::std::vec![
+                    ::jet::ops::not(::jet::ops::not(::jet::ops::eq(
+                        ::jet::ops::get("user.country"),
+                        ::jet::ops::str("DE")
+                    ))),
+                    ::jet::ops::gt(::jet::ops::float(-1.5f64), ::jet::ops::get("plan-name"))
+                ])
+            },
+        );
+    }
+
+    #[test]
+    fn it_binds_not_tighter_than_comparisons() {
+        assert_expands(
+            "!banned == false",
+            quote! {
+                ::jet::ops::eq(
+                    ::jet::ops::not(::jet::ops::get("banned")),
+                    ::jet::ops::bool(false)
+                )
+            },
+        );
+    }
+
+    #[test]
+    fn it_reads_operands_of_ge_and_le_once() {
+        let below = 0.5f64.next_down();
+        let above = 0.5f64.next_up();
+
+        assert_expands(
+            "age >= 18",
+            quote! {
+                ::jet::ops::not(::jet::ops::gt(::jet::ops::int(18i64), ::jet::ops::get("age")))
+            },
+        );
+        assert_expands(
+            "score >= 0.5",
+            quote! { ::jet::ops::gt(::jet::ops::get("score"), ::jet::ops::float(#below)) },
+        );
+        assert_expands(
+            "score <= 0.5",
+            quote! { ::jet::ops::gt(::jet::ops::float(#above), ::jet::ops::get("score")) },
+        );
+        assert!(expand("start <= end").is_err());
+        assert!(expand("scores >= [0.5]").is_err());
+    }
+
+    #[test]
+    fn it_rejects_invalid_syntax() {
+        assert!(expand("userId ==").is_err());
+        assert!(expand("userId == 1 age").is_err());
+        assert!(expand("roles == []").is_err());
+        assert!(expand("roles == [1, \"a\"]").is_err());
+        assert!(expand("len(roles) > 1").is_err());
+        assert!(expand("-\"a\" == 1").is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Field, Fields, LitStr, Result};
+
+pub fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "JetContext can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "JetContext can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut write_fields = Vec::new();
+    let mut write_schemas = Vec::new();
+
+    for field in fields {
+        let ident = &field.ident;
+        let field_type = &field.ty;
+        let name = field_name(field)?;
+
+        write_fields.push(quote! {
+            ::jet::ContextField::write_field(
+                &self.#ident,
+                &::jet::context_path(prefix, #name),
+                context,
+            );
+        });
+        write_schemas.push(quote! {
+            <#field_type as ::jet::ContextField>::write_field_schema(
+                &::jet::context_path(prefix, #name),
+                nullable,
+                schema,
+            );
+        });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::jet::JetContext for #ident #type_generics #where_clause {
+            fn write_context(&self, prefix: &str, context: &mut ::jet::Context) {
+                #(#write_fields)*
+            }
+
+            fn write_schema(prefix: &str, nullable: bool, schema: &mut ::jet::Schema) {
+                #(#write_schemas)*
+            }
+        }
+    })
+}
+
+fn field_name(field: &Field) -> Result<String> {
+    let mut name = field
+        .ident
+        .as_ref()
+        .map(|ident| ident.to_string())
+        .unwrap_or_default();
+
+    for attr in &field.attrs {
+        if !attr.path().is_ident("jet") {
+            continue;
+        }
+
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                name = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else {
+                Err(meta.error("unsupported jet attribute"))
+            }
+        })?;
+    }
+
+    Ok(name)
+}
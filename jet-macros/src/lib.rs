@@ -0,0 +1,36 @@
+mod expression;
+mod jet_context;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Implements `jet::JetContext` for a struct with named fields.
+///
+/// Fields can be renamed with `#[jet(rename = "name")]`. Nested structs must implement
+/// `JetContext` themselves and `Option` fields are left out of the context when `None`.
+#[proc_macro_derive(JetContext, attributes(jet))]
+pub fn derive_jet_context(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    jet_context::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Builds a `Box<dyn Expression>` from Rust-like syntax. Only the syntax is checked at
+/// compile time: types are checked by `eval_type()` or when the expression is evaluated.
+///
+/// ```ignore
+/// let expression = jet!(userId == 1 && (age > 18 || user.country != "DE"));
+/// ```
+///
+/// Supports `&&`, `||`, `!`, `==`, `!=`, `>`, `>=`, `<`, `<=`, literals, arrays of
+/// literals and dotted context names, with the same precedence as in Rust. Names which
+/// are not Rust identifiers can be read with `get("name")`. `>=` and `<=` cannot compare
+/// two context names, since the ops they are built from would read one of them twice.
+#[proc_macro]
+pub fn jet(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as expression::Expr);
+
+    input.expand().into()
+}
@@ -0,0 +1,772 @@
+use crate::context::Context;
+use crate::expression::ops::{and, get_name, not};
+use crate::expression::value::Value;
+use crate::expression::Expression;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::mem::discriminant;
+
+/// Most `or` branches explored before giving up with `Unknown`.
+const MAX_BRANCHES: usize = 4096;
+
+/// Whether some context makes an expression evaluate to `true`.
+#[derive(Clone, Debug)]
+pub enum Satisfiability {
+    /// Evaluating the expression against the witness gives `true`.
+    Satisfiable(Context),
+    Unsatisfiable,
+    /// The expression uses ops the analysis does not understand, or is too large.
+    Unknown,
+}
+
+impl Satisfiability {
+    pub fn is_satisfiable(&self) -> bool {
+        matches!(self, Satisfiability::Satisfiable(_))
+    }
+
+    pub fn is_unsatisfiable(&self) -> bool {
+        matches!(self, Satisfiability::Unsatisfiable)
+    }
+
+    pub fn witness(&self) -> Option<&Context> {
+        match self {
+            Satisfiability::Satisfiable(witness) => Some(witness),
+            _ => None,
+        }
+    }
+}
+
+/// Whether every context which makes one expression `true` makes another one `true`.
+#[derive(Clone, Debug)]
+pub enum Implication {
+    Holds,
+    /// A context which makes the premise `true` and the conclusion `false`.
+    Fails(Context),
+    Unknown,
+}
+
+impl Implication {
+    pub fn holds(&self) -> bool {
+        matches!(self, Implication::Holds)
+    }
+
+    pub fn counterexample(&self) -> Option<&Context> {
+        match self {
+            Implication::Fails(counterexample) => Some(counterexample),
+            _ => None,
+        }
+    }
+}
+
+/// Whether some context makes `expression` evaluate to `true`.
+///
+/// `and`, `or` and `not` are split into branches, and each branch is checked with
+/// interval reasoning over `gt` and set reasoning over `eq` between a `get` and a
+/// literal, per context key. Membership is written as an `or` of `eq`s. Other ops are
+/// assumed to be true either way, so they can only lead to `Unknown`, never to a wrong
+/// `Unsatisfiable`. Since evaluation does not check types, a key may hold a value of any
+/// type, including NaN for floats, and `Unsatisfiable` is only returned when no value of
+/// any type satisfies a branch. A witness is only returned after evaluating `expression`
+/// against it.
+pub fn satisfiability(expression: &dyn Expression) -> Satisfiability {
+    let formula = Formula::of(
+        expression.partially_evaluate(&Context::new()).as_ref(),
+        true,
+    );
+    let mut search = Search {
+        expression,
+        branches: 0,
+    };
+
+    match search.explore(vec![&formula], Vec::new()) {
+        Branch::Satisfiable(witness) => Satisfiability::Satisfiable(witness),
+        Branch::Unsatisfiable => Satisfiability::Unsatisfiable,
+        Branch::Unknown => Satisfiability::Unknown,
+    }
+}
+
+/// Whether some context makes both `left` and `right` evaluate to `true`, e.g. whether
+/// two targeting segments share a user.
+pub fn overlap(left: &dyn Expression, right: &dyn Expression) -> Satisfiability {
+    satisfiability(and([left.clone_box(), right.clone_box()]).as_ref())
+}
+
+/// Whether no context makes `premise` evaluate to `true` and `conclusion` to `false`.
+pub fn implication(premise: &dyn Expression, conclusion: &dyn Expression) -> Implication {
+    match satisfiability(and([premise.clone_box(), not(conclusion.clone_box())]).as_ref()) {
+        Satisfiability::Satisfiable(counterexample) => Implication::Fails(counterexample),
+        Satisfiability::Unsatisfiable => Implication::Holds,
+        Satisfiability::Unknown => Implication::Unknown,
+    }
+}
+
+/// Condition on the value of a context key. `NotGt` is not `Le`, since NaN is neither
+/// greater than nor less than anything.
+#[derive(Clone, Debug)]
+enum Constraint {
+    Eq(Value),
+    Ne(Value),
+    Gt(Value),
+    NotGt(Value),
+    Lt(Value),
+    NotLt(Value),
+}
+
+impl Constraint {
+    fn literal(&self) -> &Value {
+        match self {
+            Constraint::Eq(literal)
+            | Constraint::Ne(literal)
+            | Constraint::Gt(literal)
+            | Constraint::NotGt(literal)
+            | Constraint::Lt(literal)
+            | Constraint::NotLt(literal) => literal,
+        }
+    }
+
+    /// Compares like `eq` and `gt` do.
+    fn holds(&self, value: &Value) -> bool {
+        match self {
+            Constraint::Eq(literal) => value == literal,
+            Constraint::Ne(literal) => value != literal,
+            Constraint::Gt(literal) => value > literal,
+            Constraint::NotGt(literal) => value.partial_cmp(literal) != Some(Ordering::Greater),
+            Constraint::Lt(literal) => value < literal,
+            Constraint::NotLt(literal) => value.partial_cmp(literal) != Some(Ordering::Less),
+        }
+    }
+}
+
+/// Negation normal form of an expression. Every literal is true for at least the
+/// contexts which make its op true, so a branch which cannot be true really cannot be.
+#[derive(Debug)]
+enum Formula {
+    Const(bool),
+    Literal(String, Constraint),
+    And(Vec<Formula>),
+    Or(Vec<Formula>),
+}
+
+impl Formula {
+    /// Formula which holds when `expression` evaluates to `positive`.
+    fn of(expression: &dyn Expression, positive: bool) -> Formula {
+        if let Some(value) = expression.as_value() {
+            return Formula::Const(value.as_bool() == Some(positive));
+        }
+
+        if let Some(name) = get_name(expression) {
+            return Formula::Literal(name, Constraint::Eq(Value::Bool(positive)));
+        }
+
+        let args = expression.args();
+
+        match (expression.name(), args.as_slice()) {
+            ("not", [arg]) => Formula::of(arg.as_ref(), !positive),
+            ("and" | "or", args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| Formula::of(arg.as_ref(), positive))
+                    .collect();
+
+                if (expression.name() == "and") == positive {
+                    Formula::And(args)
+                } else {
+                    Formula::Or(args)
+                }
+            }
+            (op @ ("eq" | "gt"), [left, right]) => {
+                let (name, literal, flipped) = match (get_name(left.as_ref()), right.as_value()) {
+                    (Some(name), Some(literal)) => (name, literal.clone(), false),
+                    _ => match (left.as_value(), get_name(right.as_ref())) {
+                        (Some(literal), Some(name)) => (name, literal.clone(), true),
+                        _ => return Formula::Const(true),
+                    },
+                };
+
+                // NaN is not equal to anything, but values of other types are still
+                // ordered with it by type.
+                if op == "eq" && literal.partial_cmp(&literal).is_none() {
+                    return Formula::Const(!positive);
+                }
+
+                let constraint = match (op, flipped, positive) {
+                    ("eq", _, true) => Constraint::Eq(literal),
+                    ("eq", _, false) => Constraint::Ne(literal),
+                    _ if !is_scalar(&literal) => return Formula::Const(true),
+                    (_, false, true) => Constraint::Gt(literal),
+                    (_, false, false) => Constraint::NotGt(literal),
+                    (_, true, true) => Constraint::Lt(literal),
+                    (_, true, false) => Constraint::NotLt(literal),
+                };
+
+                Formula::Literal(name, constraint)
+            }
+            _ => Formula::Const(true),
+        }
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::Bool(_) | Value::Int(_) | Value::Float(_) | Value::Str(_)
+    )
+}
+
+enum Branch {
+    Satisfiable(Context),
+    Unsatisfiable,
+    Unknown,
+}
+
+struct Search<'a> {
+    expression: &'a dyn Expression,
+    branches: usize,
+}
+
+impl Search<'_> {
+    fn explore<'f>(
+        &mut self,
+        mut pending: Vec<&'f Formula>,
+        mut literals: Vec<(&'f str, &'f Constraint)>,
+    ) -> Branch {
+        while let Some(formula) = pending.pop() {
+            match formula {
+                Formula::Const(true) => {}
+                Formula::Const(false) => return Branch::Unsatisfiable,
+                Formula::Literal(name, constraint) => {
+                    literals.push((name, constraint));
+
+                    if let Solution::Empty = solve(&constraints_of(&literals, name)) {
+                        return Branch::Unsatisfiable;
+                    }
+                }
+                Formula::And(args) => pending.extend(args.iter().rev()),
+                Formula::Or(args) => {
+                    let mut unknown = false;
+
+                    for arg in args {
+                        self.branches += 1;
+
+                        if self.branches > MAX_BRANCHES {
+                            return Branch::Unknown;
+                        }
+
+                        let mut branch = pending.clone();
+                        branch.push(arg);
+
+                        match self.explore(branch, literals.clone()) {
+                            Branch::Satisfiable(witness) => return Branch::Satisfiable(witness),
+                            Branch::Unsatisfiable => {}
+                            Branch::Unknown => unknown = true,
+                        }
+                    }
+
+                    return if unknown {
+                        Branch::Unknown
+                    } else {
+                        Branch::Unsatisfiable
+                    };
+                }
+            }
+        }
+
+        self.witness(&literals)
+    }
+
+    fn witness(&self, literals: &[(&str, &Constraint)]) -> Branch {
+        let mut witness = Context::new();
+        let mut unknown = false;
+        let names: BTreeSet<&str> = literals.iter().map(|(name, _)| *name).collect();
+
+        for name in names {
+            match solve(&constraints_of(literals, name)) {
+                Solution::Value(value) => {
+                    witness.insert(name, value);
+                }
+                Solution::Empty => return Branch::Unsatisfiable,
+                Solution::Unknown => unknown = true,
+            }
+        }
+
+        if unknown {
+            return Branch::Unknown;
+        }
+
+        match self.expression.eval(&witness) {
+            Ok(Value::Bool(true)) => Branch::Satisfiable(witness),
+            _ => Branch::Unknown,
+        }
+    }
+}
+
+fn constraints_of<'f>(literals: &[(&'f str, &'f Constraint)], name: &str) -> Vec<&'f Constraint> {
+    literals
+        .iter()
+        .filter(|(literal_name, _)| *literal_name == name)
+        .map(|(_, constraint)| *constraint)
+        .collect()
+}
+
+enum Solution {
+    Value(Value),
+    Empty,
+    Unknown,
+}
+
+/// A value for one key which satisfies every constraint on it. Evaluation does not
+/// check types, so values of every type are tried, those of the literals first.
+fn solve(constraints: &[&Constraint]) -> Solution {
+    let holds = |value: &Value| constraints.iter().all(|constraint| constraint.holds(value));
+
+    // `holds()` compares exactly like evaluation does, so an `eq` settles the key even
+    // when the other literals have other types.
+    if let Some(value) = constraints.iter().find_map(|constraint| match constraint {
+        Constraint::Eq(literal) => Some(literal),
+        _ => None,
+    }) {
+        return match holds(value) {
+            true => Solution::Value(value.clone()),
+            false => Solution::Empty,
+        };
+    }
+
+    let mut unknown = false;
+
+    for example in examples(constraints) {
+        let (same_type, other_types): (Vec<&Constraint>, Vec<&Constraint>) = constraints
+            .iter()
+            .partition(|constraint| same_type(constraint.literal(), &example));
+
+        // Values of one type compare with those of another by type alone, so `example`
+        // stands for every value of its type.
+        if !other_types
+            .iter()
+            .all(|constraint| constraint.holds(&example))
+        {
+            continue;
+        }
+
+        match solve_same_type(&example, &same_type) {
+            Solution::Value(value) => return Solution::Value(value),
+            Solution::Empty => {}
+            Solution::Unknown => unknown = true,
+        }
+    }
+
+    match unknown {
+        true => Solution::Unknown,
+        false => Solution::Empty,
+    }
+}
+
+/// One value of every type, the types of the literals first.
+fn examples(constraints: &[&Constraint]) -> Vec<Value> {
+    let mut examples: Vec<Value> = Vec::new();
+    let every_type = [
+        Value::Bool(false),
+        Value::Int(0),
+        Value::Float(0.0),
+        Value::Str(String::new()),
+        Value::BoolArray(Vec::new()),
+        Value::IntArray(Vec::new()),
+        Value::FloatArray(Vec::new()),
+        Value::StrArray(Vec::new()),
+    ];
+
+    for example in constraints
+        .iter()
+        .map(|constraint| empty_value(constraint.literal()))
+        .chain(every_type)
+    {
+        if !examples.iter().any(|other| same_type(other, &example)) {
+            examples.push(example);
+        }
+    }
+
+    examples
+}
+
+fn same_type(left: &Value, right: &Value) -> bool {
+    discriminant(left) == discriminant(right)
+}
+
+/// A value of the type of `example` which satisfies `constraints`, whose literals all
+/// have that type.
+fn solve_same_type(example: &Value, constraints: &[&Constraint]) -> Solution {
+    if constraints.is_empty() {
+        return Solution::Value(example.clone());
+    }
+
+    let holds = |value: &Value| constraints.iter().all(|constraint| constraint.holds(value));
+    let excluded: Vec<&Value> = constraints
+        .iter()
+        .filter_map(|constraint| match constraint {
+            Constraint::Ne(literal) => Some(literal),
+            _ => None,
+        })
+        .collect();
+
+    match example {
+        Value::Int(_) => solve_int(constraints, &excluded),
+        Value::Bool(_) => first_holding([Value::Bool(true), Value::Bool(false)], holds)
+            .map_or(Solution::Empty, Solution::Value),
+        Value::Float(_) => {
+            // No float is greater or less than NaN, and every float is not.
+            if constraints.iter().any(|constraint| {
+                matches!(constraint, Constraint::Gt(literal) | Constraint::Lt(literal) if is_nan(literal))
+            }) {
+                return Solution::Empty;
+            }
+
+            let ordered: Vec<&Constraint> = constraints
+                .iter()
+                .filter(|constraint| !is_nan(constraint.literal()))
+                .copied()
+                .collect();
+
+            match solve_ordered(example, &ordered, &excluded, holds) {
+                Solution::Value(value) => Solution::Value(value),
+                _ if holds(&Value::Float(f64::NAN)) => Solution::Value(Value::Float(f64::NAN)),
+                solution => solution,
+            }
+        }
+        Value::Str(_) => solve_ordered(example, constraints, &excluded, holds),
+        _ => {
+            let mut candidates = vec![example.clone()];
+            candidates.extend(excluded.iter().map(|value| extend_array(value)));
+
+            first_holding(candidates, holds).map_or(Solution::Unknown, Solution::Value)
+        }
+    }
+}
+
+fn is_nan(value: &Value) -> bool {
+    matches!(value, Value::Float(content) if content.is_nan())
+}
+
+/// A float or string within the bounds. `Empty` means that no value other than NaN
+/// satisfies `constraints`.
+fn solve_ordered<F>(
+    example: &Value,
+    constraints: &[&Constraint],
+    excluded: &[&Value],
+    holds: F,
+) -> Solution
+where
+    F: Fn(&Value) -> bool,
+{
+    let (lower, upper) = bounds(constraints);
+
+    if let (Some((lower, lower_strict)), Some((upper, upper_strict))) = (lower, upper) {
+        match lower.partial_cmp(upper) {
+            Some(Ordering::Greater) => return Solution::Empty,
+            Some(Ordering::Equal) if lower_strict || upper_strict => return Solution::Empty,
+            _ => {}
+        }
+    }
+
+    if let Some((Value::Str(upper), true)) = upper {
+        if upper.is_empty() {
+            return Solution::Empty;
+        }
+    }
+
+    match first_holding(candidates(example, lower, upper, excluded), holds) {
+        Some(value) => Solution::Value(value),
+        None if lower.map(|(lower, _)| lower) == upper.map(|(upper, _)| upper) => Solution::Empty,
+        None => Solution::Unknown,
+    }
+}
+
+fn first_holding<I, F>(candidates: I, holds: F) -> Option<Value>
+where
+    I: IntoIterator<Item = Value>,
+    F: Fn(&Value) -> bool,
+{
+    candidates.into_iter().find(|candidate| holds(candidate))
+}
+
+/// Smallest range of ints allowed by the bounds, searched outward from the int closest
+/// to `0`. Each excluded value can rule out at most one candidate, so this is exact.
+fn solve_int(constraints: &[&Constraint], excluded: &[&Value]) -> Solution {
+    let mut lower = i64::MIN;
+    let mut upper = i64::MAX;
+
+    for constraint in constraints {
+        let (lower_bound, upper_bound) = match constraint {
+            Constraint::Gt(Value::Int(literal)) => (literal.checked_add(1), Some(upper)),
+            Constraint::NotLt(Value::Int(literal)) => (Some(*literal), Some(upper)),
+            Constraint::Lt(Value::Int(literal)) => (Some(lower), literal.checked_sub(1)),
+            Constraint::NotGt(Value::Int(literal)) => (Some(lower), Some(*literal)),
+            _ => continue,
+        };
+
+        match (lower_bound, upper_bound) {
+            (Some(lower_bound), Some(upper_bound)) => {
+                lower = lower.max(lower_bound);
+                upper = upper.min(upper_bound);
+            }
+            _ => return Solution::Empty,
+        }
+    }
+
+    if lower > upper {
+        return Solution::Empty;
+    }
+
+    let start = 0.clamp(lower, upper);
+
+    for distance in 0..=excluded.len() as i64 {
+        for candidate in [start.checked_add(distance), start.checked_sub(distance)]
+            .into_iter()
+            .flatten()
+        {
+            if (lower..=upper).contains(&candidate) && !excluded.contains(&&Value::Int(candidate)) {
+                return Solution::Value(Value::Int(candidate));
+            }
+        }
+    }
+
+    Solution::Empty
+}
+
+type Bound<'a> = Option<(&'a Value, bool)>;
+
+/// Tightest lower and upper bound, each with whether it is strict. Bounds hold for every
+/// value but NaN.
+fn bounds<'a>(constraints: &[&'a Constraint]) -> (Bound<'a>, Bound<'a>) {
+    let mut lower: Bound = None;
+    let mut upper: Bound = None;
+
+    for constraint in constraints {
+        let (bound, strict, is_lower) = match constraint {
+            Constraint::Gt(literal) => (literal, true, true),
+            Constraint::NotLt(literal) => (literal, false, true),
+            Constraint::Lt(literal) => (literal, true, false),
+            Constraint::NotGt(literal) => (literal, false, false),
+            _ => continue,
+        };
+        let current = if is_lower { &mut lower } else { &mut upper };
+        let tighter = match current {
+            None => true,
+            Some((value, current_strict)) => match bound.partial_cmp(value) {
+                Some(Ordering::Equal) => strict && !*current_strict,
+                Some(Ordering::Greater) => is_lower,
+                Some(Ordering::Less) => !is_lower,
+                None => false,
+            },
+        };
+
+        if tighter {
+            *current = Some((bound, strict));
+        }
+    }
+
+    (lower, upper)
+}
+
+/// Floats or strings around the bounds and excluded values.
+fn candidates(first: &Value, lower: Bound, upper: Bound, excluded: &[&Value]) -> Vec<Value> {
+    let mut anchors: Vec<&Value> = excluded.to_vec();
+    anchors.extend(lower.map(|(lower, _)| lower));
+    anchors.extend(upper.map(|(upper, _)| upper));
+
+    match first {
+        Value::Float(_) => {
+            let mut candidates = vec![0.0];
+
+            if let (Some((Value::Float(lower), _)), Some((Value::Float(upper), _))) = (lower, upper)
+            {
+                candidates.push(lower + (upper - lower) / 2.0);
+            }
+
+            for anchor in anchors {
+                if let Value::Float(anchor) = anchor {
+                    candidates.extend([
+                        *anchor,
+                        anchor + 1.0,
+                        anchor - 1.0,
+                        anchor * 2.0 + 1.0,
+                        anchor * 2.0 - 1.0,
+                    ]);
+                }
+            }
+
+            candidates.into_iter().map(Value::Float).collect()
+        }
+        _ => {
+            let mut candidates = vec!["".to_string(), "a".to_string()];
+
+            for anchor in anchors {
+                if let Value::Str(anchor) = anchor {
+                    let mut shorter = anchor.clone();
+                    shorter.pop();
+
+                    candidates.extend([
+                        anchor.clone(),
+                        format!("{}a", anchor),
+                        format!("{}\u{0}", anchor),
+                        shorter,
+                    ]);
+                }
+            }
+
+            candidates.into_iter().map(Value::Str).collect()
+        }
+    }
+}
+
+/// `false`, zero, an empty string or an empty array of the type of `value`.
+fn empty_value(value: &Value) -> Value {
+    match value {
+        Value::Bool(_) => Value::Bool(false),
+        Value::Int(_) => Value::Int(0),
+        Value::Float(_) => Value::Float(0.0),
+        Value::Str(_) => Value::Str(String::new()),
+        Value::BoolArray(_) => Value::BoolArray(Vec::new()),
+        Value::IntArray(_) => Value::IntArray(Vec::new()),
+        Value::FloatArray(_) => Value::FloatArray(Vec::new()),
+        Value::StrArray(_) => Value::StrArray(Vec::new()),
+    }
+}
+
+/// `value` with one more item, so it differs from every array no longer than `value`.
+fn extend_array(value: &Value) -> Value {
+    let mut value = value.clone();
+
+    match &mut value {
+        Value::BoolArray(items) => items.push(false),
+        Value::IntArray(items) => items.push(0),
+        Value::FloatArray(items) => items.push(0.0),
+        Value::StrArray(items) => items.push(String::new()),
+        _ => {}
+    }
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::ops::*;
+    use crate::parser::parse;
+
+    #[test]
+    fn it_finds_witnesses() {
+        let expression = parse(
+            r#"{"and": [
+                {"gt": [{"get": ["age"]}, 17]},
+                {"not": [{"gt": [{"get": ["age"]}, 18]}]},
+                {"not": [{"eq": [{"get": ["country"]}, "DE"]}]},
+                {"or": [{"eq": [{"get": ["plan"]}, "pro"]}, {"eq": [{"get": ["plan"]}, "team"]}]},
+                {"not": [{"get": ["banned"]}]},
+                {"gt": [{"get": ["score"]}, 0.5]},
+                {"gt": [10.0, {"get": ["score"]}]}
+            ]}"#,
+        )
+        .unwrap();
+
+        let result = satisfiability(expression.as_ref());
+        let witness = result.witness().unwrap();
+
+        assert!(expression.eval_bool(witness).unwrap());
+        assert_eq!(witness.get("age"), Some(&Value::Int(18)));
+        assert_eq!(witness.get("plan"), Some(&Value::Str("pro".to_string())));
+        assert_eq!(witness.get("banned"), Some(&Value::Bool(false)));
+    }
+
+    #[test]
+    fn it_proves_unsatisfiability() {
+        let expressions = [
+            and([eq(get("x"), int(1)), eq(get("x"), int(2))]),
+            and([gt(get("age"), int(17)), gt(int(18), get("age"))]),
+            and([
+                or([eq(get("plan"), str("pro")), eq(get("plan"), str("team"))]),
+                not(eq(get("plan"), str("pro"))),
+                not(eq(get("plan"), str("team"))),
+            ]),
+            and([gt(get("score"), float(1.0)), gt(float(1.0), get("score"))]),
+            and([get("beta"), not(get("beta"))]),
+            and([eq(get("x"), int(1)), eq(get("x"), str("1"))]),
+            and([gt(get("x"), str("z")), gt(int(1), get("x"))]),
+        ];
+
+        for expression in expressions {
+            assert!(
+                satisfiability(expression.as_ref()).is_unsatisfiable(),
+                "{:?}",
+                expression.to_json()
+            );
+        }
+    }
+
+    #[test]
+    fn it_solves_constraints_with_literals_of_several_types() {
+        let expressions = [
+            and([not(eq(get("x"), int(1))), not(eq(get("x"), str("a")))]),
+            and([gt(get("s"), float(0.5)), gt(get("s"), int(1))]),
+            gt(get("x"), int(i64::MAX)),
+            gt(get("x"), float(f64::NAN)),
+        ];
+
+        for expression in expressions {
+            let result = satisfiability(expression.as_ref());
+
+            assert!(expression.eval_bool(result.witness().unwrap()).unwrap());
+        }
+    }
+
+    #[test]
+    fn it_finds_nan_witnesses() {
+        let expression = and([
+            not(gt(get("score"), float(1.0))),
+            not(gt(float(1.0), get("score"))),
+            not(eq(get("score"), float(1.0))),
+        ]);
+
+        let result = satisfiability(expression.as_ref());
+        let witness = result.witness().unwrap();
+
+        assert!(expression.eval_bool(witness).unwrap());
+        assert!(matches!(witness.get("score"), Some(Value::Float(score)) if score.is_nan()));
+    }
+
+    #[test]
+    fn it_reports_unknown_for_opaque_ops() {
+        let expression = and([gt(get("a"), get("b")), gt(get("b"), get("a"))]);
+
+        assert!(matches!(
+            satisfiability(expression.as_ref()),
+            Satisfiability::Unknown
+        ));
+    }
+
+    #[test]
+    fn it_checks_overlap_of_segments() {
+        let beta = and([gt(get("userId"), int(100)), eq(get("country"), str("DE"))]);
+        let control = or([
+            gt(int(101), get("userId")),
+            not(eq(get("country"), str("DE"))),
+        ]);
+        let adults = gt(get("userId"), int(500));
+
+        assert!(overlap(beta.as_ref(), control.as_ref()).is_unsatisfiable());
+
+        let witness = overlap(beta.as_ref(), adults.as_ref());
+        let witness = witness.witness().unwrap();
+        assert!(beta.eval_bool(witness).unwrap() && adults.eval_bool(witness).unwrap());
+    }
+
+    #[test]
+    fn it_checks_implication() {
+        let premise = and([gt(get("age"), int(20)), eq(get("country"), str("DE"))]);
+
+        assert!(implication(premise.as_ref(), gt(get("age"), int(18)).as_ref()).holds());
+
+        let result = implication(premise.as_ref(), gt(get("age"), int(30)).as_ref());
+        let counterexample = result.counterexample().unwrap();
+        assert!(premise.eval_bool(counterexample).unwrap());
+        assert!(!gt(get("age"), int(30)).eval_bool(counterexample).unwrap());
+    }
+}
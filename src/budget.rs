@@ -0,0 +1,220 @@
+use crate::expression::value::Value;
+use crate::expression::{EvalError, EvalErrorKind, EvalResult};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Shared flag used to cancel running evaluations from another thread.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Limits the cost of evaluating expressions against a `Context`.
+///
+/// Steps are counted across every evaluation that uses the budget, clones included,
+/// so a budget set on a context is meant to be created once per request. Use
+/// `Expression::eval_with_budget()` to count the steps of each evaluation separately.
+#[derive(Clone, Debug, Default)]
+pub struct EvalBudget {
+    max_steps: Option<u64>,
+    max_value_size: Option<usize>,
+    deadline: Option<Instant>,
+    cancellation_token: Option<CancellationToken>,
+    steps: Arc<AtomicU64>,
+}
+
+impl EvalBudget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_max_steps(self, max_steps: u64) -> Self {
+        EvalBudget {
+            max_steps: Some(max_steps),
+            ..self
+        }
+    }
+
+    /// Largest `Value::size()` of a literal or of a value read from the context. Values
+    /// computed by ops are not checked, since the ops in this crate only compute bools.
+    pub fn set_max_value_size(self, max_value_size: usize) -> Self {
+        EvalBudget {
+            max_value_size: Some(max_value_size),
+            ..self
+        }
+    }
+
+    pub fn set_deadline(self, deadline: Instant) -> Self {
+        EvalBudget {
+            deadline: Some(deadline),
+            ..self
+        }
+    }
+
+    pub fn set_timeout(self, timeout: Duration) -> Self {
+        self.set_deadline(Instant::now() + timeout)
+    }
+
+    pub fn set_cancellation_token(self, cancellation_token: CancellationToken) -> Self {
+        EvalBudget {
+            cancellation_token: Some(cancellation_token),
+            ..self
+        }
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps.load(Ordering::Relaxed)
+    }
+
+    /// Same limits, with a step counter of its own starting from zero.
+    pub(crate) fn restarted(&self) -> Self {
+        EvalBudget {
+            steps: Arc::new(AtomicU64::new(0)),
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn check_step(&self) -> EvalResult<()> {
+        let steps = self.steps.fetch_add(1, Ordering::Relaxed) + 1;
+
+        if let Some(max_steps) = self.max_steps {
+            if steps > max_steps {
+                return Err(EvalError {
+                    error_kind: EvalErrorKind::StepLimitExceeded { max_steps },
+                });
+            }
+        }
+
+        if let Some(cancellation_token) = &self.cancellation_token {
+            if cancellation_token.is_cancelled() {
+                return Err(EvalError {
+                    error_kind: EvalErrorKind::Cancelled,
+                });
+            }
+        }
+
+        if let Some(deadline) = self.deadline {
+            if Instant::now() >= deadline {
+                return Err(EvalError {
+                    error_kind: EvalErrorKind::DeadlineExceeded,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn check_value_size(&self, value: &Value) -> EvalResult<()> {
+        match self.max_value_size {
+            Some(max_value_size) if value.size() > max_value_size => Err(EvalError {
+                error_kind: EvalErrorKind::ValueSizeLimitExceeded {
+                    max_value_size,
+                    actual: value.size(),
+                },
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+    use crate::expression::ops::*;
+
+    #[test]
+    fn it_limits_steps() {
+        let expression = eq(int(1), gt(int(2), int(1)));
+        let budget = EvalBudget::new().set_max_steps(4);
+        let context = Context::new().set_budget(budget.clone());
+
+        assert!(matches!(
+            expression.eval(&context).unwrap_err().kind(),
+            EvalErrorKind::StepLimitExceeded { max_steps: 4 }
+        ));
+        assert_eq!(budget.steps(), 5);
+    }
+
+    #[test]
+    fn it_shares_steps_between_evaluations() {
+        let expression = eq(int(1), int(1));
+        let context = Context::new().set_budget(EvalBudget::new().set_max_steps(5));
+
+        assert!(expression.eval(&context).is_ok());
+        assert!(expression.eval(&context).is_err());
+    }
+
+    #[test]
+    fn it_counts_steps_per_evaluation_with_budget() {
+        let expression = eq(int(1), int(1));
+        let context = Context::new();
+        let budget = EvalBudget::new().set_max_steps(5);
+
+        assert!(expression.eval_with_budget(&context, &budget).is_ok());
+        assert!(expression.eval_with_budget(&context, &budget).is_ok());
+        assert!(matches!(
+            eq(eq(int(1), int(1)), expression)
+                .eval_with_budget(&context, &budget)
+                .unwrap_err()
+                .kind(),
+            EvalErrorKind::StepLimitExceeded { max_steps: 5 }
+        ));
+        assert_eq!(budget.steps(), 0);
+    }
+
+    #[test]
+    fn it_limits_value_size() {
+        let expression = eq(get("ids"), int_array([1, 2]));
+        let context = Context::new()
+            .set_int_array("ids", [1, 2, 3])
+            .set_budget(EvalBudget::new().set_max_value_size(2));
+
+        assert!(matches!(
+            expression.eval(&context).unwrap_err().kind(),
+            EvalErrorKind::ValueSizeLimitExceeded {
+                max_value_size: 2,
+                actual: 3
+            }
+        ));
+    }
+
+    #[test]
+    fn it_stops_when_cancelled() {
+        let token = CancellationToken::new();
+        let context =
+            Context::new().set_budget(EvalBudget::new().set_cancellation_token(token.clone()));
+
+        assert!(int(1).eval(&context).is_ok());
+        token.cancel();
+        assert!(matches!(
+            int(1).eval(&context).unwrap_err().kind(),
+            EvalErrorKind::Cancelled
+        ));
+    }
+
+    #[test]
+    fn it_stops_after_deadline() {
+        let context = Context::new().set_budget(EvalBudget::new().set_deadline(Instant::now()));
+
+        assert!(matches!(
+            int(1).eval(&context).unwrap_err().kind(),
+            EvalErrorKind::DeadlineExceeded
+        ));
+    }
+}
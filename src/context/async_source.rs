@@ -0,0 +1,270 @@
+use crate::context::{Context, ResolverError};
+use crate::expression::value::Value;
+use crate::expression::{BoxFuture, EvalError, EvalErrorKind, EvalResult};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::Poll;
+
+/// Asynchronous counterpart of `ContextSource`, used by `Expression::eval_async()`
+/// for names that the `Context` does not provide.
+pub trait AsyncContextSource: Send + Sync {
+    fn lookup<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<Option<Value>, ResolverError>>;
+}
+
+/// Fetches every name the context does not provide concurrently and returns a context
+/// which serves the fetched values after its own.
+pub(crate) async fn prefetch(
+    context: &Context,
+    source: &dyn AsyncContextSource,
+    names: Option<Vec<String>>,
+) -> EvalResult<Context> {
+    let names: Vec<String> = names
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|name| !context.contains_key(name))
+        .collect();
+
+    if names.is_empty() {
+        return Ok(context.clone());
+    }
+
+    let lookups = names.iter().map(|name| source.lookup(name)).collect();
+    let mut fetched = HashMap::new();
+
+    for (name, result) in names.iter().zip(JoinAll::new(lookups).await) {
+        match result {
+            Ok(Some(value)) => {
+                fetched.insert(name.clone(), value);
+            }
+            Ok(None) => {}
+            Err(source) => {
+                return Err(EvalError {
+                    error_kind: EvalErrorKind::ResolverFailed {
+                        name: name.clone(),
+                        source,
+                    },
+                })
+            }
+        }
+    }
+
+    Ok(context.clone().add_source(fetched))
+}
+
+/// Remembers what a source returned, so that the args of `and` and `or`, which prefetch
+/// one at a time, do not look up names which earlier args already fetched.
+pub(crate) struct FetchedSource<'a> {
+    source: &'a dyn AsyncContextSource,
+    fetched: Mutex<HashMap<String, Option<Value>>>,
+}
+
+impl<'a> FetchedSource<'a> {
+    pub(crate) fn new(source: &'a dyn AsyncContextSource) -> Self {
+        Self {
+            source,
+            fetched: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl AsyncContextSource for FetchedSource<'_> {
+    fn lookup<'a>(&'a self, path: &'a str) -> BoxFuture<'a, Result<Option<Value>, ResolverError>> {
+        Box::pin(async move {
+            if let Some(value) = self.fetched.lock().unwrap().get(path) {
+                return Ok(value.clone());
+            }
+
+            let value = self.source.lookup(path).await?;
+            self.fetched
+                .lock()
+                .unwrap()
+                .insert(path.to_string(), value.clone());
+            Ok(value)
+        })
+    }
+}
+
+/// Polls all futures on every wake-up and yields their outputs in input order.
+struct JoinAll<'a, T> {
+    futures: Vec<Option<BoxFuture<'a, T>>>,
+    outputs: Vec<Option<T>>,
+}
+
+impl<'a, T> JoinAll<'a, T> {
+    fn new(futures: Vec<BoxFuture<'a, T>>) -> Self {
+        Self {
+            outputs: futures.iter().map(|_| None).collect(),
+            futures: futures.into_iter().map(Some).collect(),
+        }
+    }
+}
+
+impl<'a, T> Future for JoinAll<'a, T>
+where
+    T: Unpin,
+{
+    type Output = Vec<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        for (future, output) in this.futures.iter_mut().zip(this.outputs.iter_mut()) {
+            if let Some(pending) = future {
+                if let Poll::Ready(value) = pending.as_mut().poll(cx) {
+                    *output = Some(value);
+                    *future = None;
+                }
+            }
+        }
+
+        if this.futures.iter().all(Option::is_none) {
+            Poll::Ready(this.outputs.iter_mut().filter_map(Option::take).collect())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::ops::*;
+    use crate::expression::test_utils::block_on;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// In-memory stand-in for a database, which yields once before answering
+    /// so that concurrent lookups overlap.
+    #[derive(Default)]
+    struct Store {
+        values: HashMap<String, Value>,
+        lookups: Mutex<Vec<String>>,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    impl Store {
+        fn new(values: &[(&str, Value)]) -> Self {
+            Self {
+                values: values
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.clone()))
+                    .collect(),
+                ..Self::default()
+            }
+        }
+
+        fn lookups(&self) -> Vec<String> {
+            self.lookups.lock().unwrap().clone()
+        }
+    }
+
+    impl AsyncContextSource for Store {
+        fn lookup<'a>(
+            &'a self,
+            path: &'a str,
+        ) -> BoxFuture<'a, Result<Option<Value>, ResolverError>> {
+            Box::pin(async move {
+                self.lookups.lock().unwrap().push(path.to_string());
+                let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+
+                YieldOnce(false).await;
+
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                match path {
+                    "broken" => Err(ResolverError::from(Box::from("connection reset"))),
+                    _ => Ok(self.values.get(path).cloned()),
+                }
+            })
+        }
+    }
+
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<()> {
+            if self.0 {
+                Poll::Ready(())
+            } else {
+                self.0 = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn it_fetches_independent_gets_concurrently() {
+        let store = Store::new(&[("a", Value::Int(1)), ("b", Value::Int(1))]);
+        let expression = eq(get("a"), get("b"));
+
+        let result = block_on(expression.eval_async(&Context::new(), &store));
+
+        assert_eq!(result.unwrap(), Value::Bool(true));
+        assert_eq!(store.max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn it_short_circuits_and_or() {
+        let store = Store::new(&[("a", Value::Int(1)), ("b", Value::Int(2))]);
+        let expression = or([
+            not(or([eq(get("a"), int(1)), eq(get("b"), int(2))])),
+            and([eq(get("a"), int(2)), eq(get("b"), int(2))]),
+            eq(get("a"), int(1)),
+            eq(get("c"), int(1)),
+        ]);
+
+        let result = block_on(expression.eval_async(&Context::new(), &store));
+
+        assert_eq!(result.unwrap(), Value::Bool(true));
+        assert!(store.lookups().iter().all(|name| name == "a"));
+    }
+
+    #[test]
+    fn it_fetches_names_once_across_and_or_args() {
+        let store = Store::new(&[("a", Value::Int(1)), ("b", Value::Int(2))]);
+        let expression = and([
+            eq(get("a"), int(1)),
+            or([eq(get("b"), int(1)), eq(get("a"), get("b"))]),
+            not(eq(get("b"), get("a"))),
+        ]);
+
+        let result = block_on(expression.eval_async(&Context::new(), &store));
+
+        assert_eq!(result.unwrap(), Value::Bool(false));
+        assert_eq!(store.lookups(), ["a", "b"]);
+    }
+
+    #[test]
+    fn it_prefers_context_values() {
+        let store = Store::new(&[("a", Value::Int(2))]);
+        let context = Context::new().set_int("a", 1);
+
+        let result = block_on(eq(get("a"), int(1)).eval_async(&context, &store));
+
+        assert_eq!(result.unwrap(), Value::Bool(true));
+        assert!(store.lookups().is_empty());
+    }
+
+    #[test]
+    fn it_reports_lookup_failures() {
+        let store = Store::new(&[]);
+
+        let missing = block_on(get("a").eval_async(&Context::new(), &store)).unwrap_err();
+        let broken = block_on(get("broken").eval_async(&Context::new(), &store)).unwrap_err();
+
+        assert!(matches!(
+            missing.kind(),
+            EvalErrorKind::MissingContext { name } if name == "a"
+        ));
+        assert!(matches!(
+            broken.kind(),
+            EvalErrorKind::ResolverFailed { name, .. } if name == "broken"
+        ));
+    }
+}
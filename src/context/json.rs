@@ -0,0 +1,338 @@
+use crate::context::Context;
+use crate::expression::value::Value;
+use crate::expression::Expression;
+use serde_json::{Map as JsonMap, Number as JsonNumber, Value as JsonValue};
+use std::collections::HashMap;
+use std::fmt;
+
+pub type ContextResult<T> = Result<T, ContextError>;
+
+#[derive(Debug)]
+pub struct ContextError {
+    error_kind: ContextErrorKind,
+    path: String,
+}
+
+#[derive(Debug)]
+pub enum ContextErrorKind {
+    NotAnObject,
+    UnsupportedNull,
+    InvalidNumber,
+    EmptyArray,
+    MixedArray,
+    NestedArray,
+    /// An object where a value is expected, e.g. when a JSON `ContextSource` is asked for
+    /// `user` in `{"user": {"id": 1}}`.
+    NestedObject,
+    /// Two entries flatten to the same dotted name, e.g. `{"a.b": 1, "a": {"b": 2}}`.
+    DuplicateName,
+    Serialize(serde_json::Error),
+}
+
+impl ContextError {
+    pub fn kind(&self) -> &ContextErrorKind {
+        &self.error_kind
+    }
+
+    /// Dotted path of the value which could not be converted. Empty for the document root.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error_kind {
+            ContextErrorKind::NotAnObject => write!(f, "context must be a JSON object")?,
+            ContextErrorKind::UnsupportedNull => write!(f, "null is not supported")?,
+            ContextErrorKind::InvalidNumber => write!(f, "number does not fit i64 or f64")?,
+            ContextErrorKind::EmptyArray => write!(f, "empty arrays have no item type")?,
+            ContextErrorKind::MixedArray => write!(f, "array items have different types")?,
+            ContextErrorKind::NestedArray => write!(f, "array items must be scalars")?,
+            ContextErrorKind::NestedObject => write!(f, "objects are not values")?,
+            ContextErrorKind::DuplicateName => write!(f, "name is set more than once")?,
+            ContextErrorKind::Serialize(err) => write!(f, "serialization failed: {}", err)?,
+        }
+
+        if self.path.is_empty() {
+            Ok(())
+        } else {
+            write!(f, " at {}", self.path)
+        }
+    }
+}
+
+impl std::error::Error for ContextError {}
+
+impl Context {
+    /// Builds a context from a JSON object. Nested objects are flattened into dotted
+    /// names, so `{"user": {"id": 1}}` sets `user.id`, and two entries flattening to the
+    /// same name are rejected. Arrays must be non-empty and hold scalars of a single type,
+    /// and `null` is rejected.
+    pub fn from_json(json: &JsonValue) -> ContextResult<Context> {
+        let object = match json {
+            JsonValue::Object(object) => object,
+            _ => {
+                return Err(ContextError {
+                    error_kind: ContextErrorKind::NotAnObject,
+                    path: String::new(),
+                })
+            }
+        };
+
+        let mut data = HashMap::new();
+        flatten_json_object(object, "", &mut data)?;
+
+        Ok(data.into_iter().collect())
+    }
+
+    /// Builds a context from any `Serialize` value that serializes to a JSON object,
+    /// with the same rules as `from_json()`.
+    #[cfg(feature = "serde")]
+    pub fn from_serialize<T>(value: &T) -> ContextResult<Context>
+    where
+        T: serde::Serialize + ?Sized,
+    {
+        match serde_json::to_value(value) {
+            Ok(json) => Context::from_json(&json),
+            Err(err) => Err(ContextError {
+                error_kind: ContextErrorKind::Serialize(err),
+                path: String::new(),
+            }),
+        }
+    }
+
+    /// Flat JSON object with one entry per context name. Passing it back to
+    /// `from_json()` produces an equal context as long as every float is finite and no
+    /// array is empty, since JSON has no NaN or infinity and `[]` has no item type.
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::Object(
+            self.iter()
+                .map(|(name, value)| (name.to_string(), value.to_json()))
+                .collect(),
+        )
+    }
+}
+
+fn join_path(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", prefix, name)
+    }
+}
+
+fn flatten_json_object(
+    object: &JsonMap<String, JsonValue>,
+    prefix: &str,
+    data: &mut HashMap<String, Value>,
+) -> ContextResult<()> {
+    for (name, json) in object {
+        let path = join_path(prefix, name);
+
+        match json {
+            JsonValue::Object(object) => flatten_json_object(object, &path, data)?,
+            _ => {
+                let value = json_to_value(json, &path)?;
+
+                if data.contains_key(&path) {
+                    return Err(ContextError {
+                        error_kind: ContextErrorKind::DuplicateName,
+                        path,
+                    });
+                }
+
+                data.insert(path, value);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn json_to_value(json: &JsonValue, path: &str) -> ContextResult<Value> {
+    let error = |error_kind| {
+        Err(ContextError {
+            error_kind,
+            path: path.to_string(),
+        })
+    };
+
+    match json {
+        JsonValue::Null => error(ContextErrorKind::UnsupportedNull),
+        JsonValue::Bool(content) => Ok(Value::Bool(*content)),
+        JsonValue::Number(content) => match json_number_to_value(content) {
+            Some(value) => Ok(value),
+            None => error(ContextErrorKind::InvalidNumber),
+        },
+        JsonValue::String(content) => Ok(Value::Str(content.clone())),
+        JsonValue::Array(content) => json_array_to_value(content, path),
+        JsonValue::Object(_) => error(ContextErrorKind::NestedObject),
+    }
+}
+
+fn json_number_to_value(number: &JsonNumber) -> Option<Value> {
+    if number.is_f64() {
+        number.as_f64().map(Value::Float)
+    } else {
+        number.as_i64().map(Value::Int)
+    }
+}
+
+fn json_array_to_value(content: &[JsonValue], path: &str) -> ContextResult<Value> {
+    let mut items = Vec::with_capacity(content.len());
+
+    for (position, json) in content.iter().enumerate() {
+        let item_path = format!("{}[{}]", path, position);
+
+        match json {
+            JsonValue::Array(_) | JsonValue::Object(_) => {
+                return Err(ContextError {
+                    error_kind: ContextErrorKind::NestedArray,
+                    path: item_path,
+                })
+            }
+            _ => items.push(json_to_value(json, &item_path)?),
+        }
+    }
+
+    let mixed = || ContextError {
+        error_kind: ContextErrorKind::MixedArray,
+        path: path.to_string(),
+    };
+
+    match items.first() {
+        None => Err(ContextError {
+            error_kind: ContextErrorKind::EmptyArray,
+            path: path.to_string(),
+        }),
+        Some(Value::Bool(_)) => items
+            .into_iter()
+            .map(|item| item.as_bool().ok_or_else(mixed))
+            .collect::<ContextResult<Vec<_>>>()
+            .map(Value::BoolArray),
+        Some(Value::Int(_)) => items
+            .into_iter()
+            .map(|item| item.as_int().ok_or_else(mixed))
+            .collect::<ContextResult<Vec<_>>>()
+            .map(Value::IntArray),
+        Some(Value::Float(_)) => items
+            .into_iter()
+            .map(|item| item.as_float().ok_or_else(mixed))
+            .collect::<ContextResult<Vec<_>>>()
+            .map(Value::FloatArray),
+        Some(_) => items
+            .into_iter()
+            .map(|item| item.as_str().ok_or_else(mixed))
+            .collect::<ContextResult<Vec<_>>>()
+            .map(Value::StrArray),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn it_builds_context_from_json() {
+        let context = Context::from_json(&json!({
+            "userId": 1,
+            "score": 0.5,
+            "user": {"country": "DE", "flags": {"beta": true}},
+            "roles": ["admin", "dev"],
+            "ids": [1, 2]
+        }))
+        .unwrap();
+
+        assert_eq!(context.get("userId"), Some(&Value::Int(1)));
+        assert_eq!(context.get("score"), Some(&Value::Float(0.5)));
+        assert_eq!(context.get("user.country"), Some(&Value::Str("DE".into())));
+        assert_eq!(context.get("user.flags.beta"), Some(&Value::Bool(true)));
+        assert_eq!(
+            context.get("roles"),
+            Some(&Value::StrArray(vec!["admin".into(), "dev".into()]))
+        );
+        assert_eq!(context.get("ids"), Some(&Value::IntArray(vec![1, 2])));
+    }
+
+    #[test]
+    fn it_rejects_unrepresentable_json() {
+        let assert_error = |json: JsonValue, path: &str| {
+            let error = Context::from_json(&json).unwrap_err();
+            assert_eq!(error.path(), path);
+            error
+        };
+
+        assert!(matches!(
+            assert_error(json!([1]), "").kind(),
+            ContextErrorKind::NotAnObject
+        ));
+        assert!(matches!(
+            assert_error(json!({"a": {"b": null}}), "a.b").kind(),
+            ContextErrorKind::UnsupportedNull
+        ));
+        assert!(matches!(
+            assert_error(json!({"a": []}), "a").kind(),
+            ContextErrorKind::EmptyArray
+        ));
+        assert!(matches!(
+            assert_error(json!({"a": [1, 1.5]}), "a").kind(),
+            ContextErrorKind::MixedArray
+        ));
+        assert!(matches!(
+            assert_error(json!({"a": [1, [2]]}), "a[1]").kind(),
+            ContextErrorKind::NestedArray
+        ));
+        assert!(matches!(
+            assert_error(json!({"a": u64::MAX}), "a").kind(),
+            ContextErrorKind::InvalidNumber
+        ));
+        assert!(matches!(
+            assert_error(json!({"a.b": 1, "a": {"b": 2}}), "a.b").kind(),
+            ContextErrorKind::DuplicateName
+        ));
+        assert!(matches!(
+            json_to_value(&json!({"id": 1}), "user").unwrap_err().kind(),
+            ContextErrorKind::NestedObject
+        ));
+    }
+
+    #[test]
+    fn it_round_trips_through_json() {
+        let context = Context::new()
+            .set_int("userId", 1)
+            .set_str("user.country", "DE")
+            .set_str_array("roles", ["admin".to_string()]);
+
+        let json = context.to_json();
+
+        assert_eq!(
+            json,
+            json!({"userId": 1, "user.country": "DE", "roles": ["admin"]})
+        );
+        assert_eq!(Context::from_json(&json).unwrap().to_json(), json);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_builds_context_from_serialize() {
+        #[derive(serde::Serialize)]
+        struct Request {
+            user_id: i64,
+            tags: Vec<String>,
+        }
+
+        let context = Context::from_serialize(&Request {
+            user_id: 7,
+            tags: vec!["a".into()],
+        })
+        .unwrap();
+
+        assert_eq!(context.get("user_id"), Some(&Value::Int(7)));
+        assert!(matches!(
+            Context::from_serialize(&1).unwrap_err().kind(),
+            ContextErrorKind::NotAnObject
+        ));
+    }
+}
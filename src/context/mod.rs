@@ -0,0 +1,445 @@
+mod async_source;
+mod json;
+mod resolver;
+mod source;
+
+pub use async_source::AsyncContextSource;
+pub(crate) use async_source::{prefetch, FetchedSource};
+#[cfg(feature = "serde")]
+pub(crate) use json::json_to_value;
+pub use json::{ContextError, ContextErrorKind, ContextResult};
+pub use resolver::ResolverError;
+pub use source::ContextSource;
+
+use crate::budget::EvalBudget;
+use crate::context::resolver::Resolver;
+use crate::expression::eval_type::Type;
+use crate::expression::value::Value;
+use crate::expression::{EvalError, EvalErrorKind, EvalResult};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::sync::Arc;
+
+/// Values are kept behind an `Arc`, so cloning a `Context` or sharing it between
+/// threads does not copy them. Setters copy the map only while it is shared. Values
+/// computed by resolvers are shared as well: once one clone has computed a value,
+/// every clone reads it.
+///
+/// A context created with `child()` overlays its parent: names it does not provide are
+/// looked up in the parent, which is shared rather than copied.
+#[derive(Clone, Debug)]
+pub struct Context {
+    layer: Arc<Layer>,
+    budget: Option<EvalBudget>,
+}
+
+/// Names provided by one context, on top of those of its parent. A budget is not part
+/// of the layer, so contexts can share a layer and count steps separately.
+#[derive(Clone, Debug, Default)]
+struct Layer {
+    parent: Option<Arc<Layer>>,
+    data: Arc<HashMap<String, Value>>,
+    resolvers: Arc<HashMap<String, Arc<Resolver>>>,
+    sources: Arc<Vec<Arc<dyn ContextSource>>>,
+}
+
+impl Context {
+    pub fn new() -> Self {
+        Self {
+            layer: Arc::new(Layer::default()),
+            budget: None,
+        }
+    }
+
+    /// New empty context layered on top of this one. The child does not inherit
+    /// the parent's budget.
+    pub fn child(&self) -> Context {
+        Context {
+            layer: Arc::new(Layer {
+                parent: Some(self.layer.clone()),
+                ..Layer::default()
+            }),
+            budget: None,
+        }
+    }
+
+    /// Same names as this context, evaluated under another budget.
+    pub(crate) fn with_budget(&self, budget: EvalBudget) -> Context {
+        Context {
+            layer: self.layer.clone(),
+            budget: Some(budget),
+        }
+    }
+
+    /// Whether evaluations count steps and check value sizes against a budget.
+    pub(crate) fn has_budget(&self) -> bool {
+        self.budget.is_some()
+    }
+
+    pub fn set<S, V>(mut self, name: S, value: V) -> Self
+    where
+        S: Into<String>,
+        V: Into<Value>,
+    {
+        self.insert(name, value);
+        self
+    }
+
+    pub fn insert<S, V>(&mut self, name: S, value: V) -> Option<Value>
+    where
+        S: Into<String>,
+        V: Into<Value>,
+    {
+        let layer = Arc::make_mut(&mut self.layer);
+        Arc::make_mut(&mut layer.data).insert(name.into(), value.into())
+    }
+
+    /// Removes a value set on this context. In a child context, the parent's value
+    /// for the same name becomes visible again.
+    pub fn remove(&mut self, name: &str) -> Option<Value> {
+        if !self.layer.data.contains_key(name) {
+            return None;
+        }
+
+        let layer = Arc::make_mut(&mut self.layer);
+        Arc::make_mut(&mut layer.data).remove(name)
+    }
+
+    /// Values set on this context and its parents, without values shadowed by a child.
+    /// Resolvers and sources are not included.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        let mut seen = HashSet::new();
+
+        self.layers()
+            .flat_map(|layer| layer.data.iter())
+            .filter(move |(name, _)| seen.insert(name.as_str()))
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    /// This context's layer followed by those of its parents.
+    fn layers(&self) -> impl Iterator<Item = &Layer> {
+        std::iter::successors(Some(&*self.layer), |layer| layer.parent.as_deref())
+    }
+
+    pub fn set_bool<S>(self, name: S, value: bool) -> Self
+    where
+        S: Into<String>,
+    {
+        self.set(name, Value::Bool(value))
+    }
+
+    pub fn set_bool_array<S, V>(self, name: S, value: V) -> Self
+    where
+        S: Into<String>,
+        V: Into<Vec<bool>>,
+    {
+        self.set(name, Value::BoolArray(value.into()))
+    }
+
+    pub fn set_int<S>(self, name: S, value: i64) -> Self
+    where
+        S: Into<String>,
+    {
+        self.set(name, Value::Int(value))
+    }
+
+    pub fn set_int_array<S, V>(self, name: S, value: V) -> Self
+    where
+        S: Into<String>,
+        V: Into<Vec<i64>>,
+    {
+        self.set(name, Value::IntArray(value.into()))
+    }
+
+    pub fn set_float<S>(self, name: S, value: f64) -> Self
+    where
+        S: Into<String>,
+    {
+        self.set(name, Value::Float(value))
+    }
+
+    pub fn set_float_array<S, V>(self, name: S, value: V) -> Self
+    where
+        S: Into<String>,
+        V: Into<Vec<f64>>,
+    {
+        self.set(name, Value::FloatArray(value.into()))
+    }
+
+    pub fn set_str<S, V>(self, name: S, value: V) -> Self
+    where
+        S: Into<String>,
+        V: Into<String>,
+    {
+        self.set(name, Value::Str(value.into()))
+    }
+
+    pub fn set_str_array<S, V>(self, name: S, value: V) -> Self
+    where
+        S: Into<String>,
+        V: Into<Vec<String>>,
+    {
+        self.set(name, Value::StrArray(value.into()))
+    }
+
+    /// Budget for every evaluation against this context and its clones, which share
+    /// its step count. See `Expression::eval_with_budget()` for a per-evaluation count.
+    pub fn set_budget(self, budget: EvalBudget) -> Self {
+        Context {
+            budget: Some(budget),
+            ..self
+        }
+    }
+
+    /// Backs a name with a closure that runs when `get` first reads the name. Its value is
+    /// remembered for later reads from this context and its clones, while failures are
+    /// not remembered. `eval_type()` uses the declared type and does not run the closure.
+    pub fn set_resolver<S, F, E>(mut self, name: S, value_type: Type, resolver: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn() -> Result<Value, E> + Send + Sync + 'static,
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        let layer = Arc::make_mut(&mut self.layer);
+        Arc::make_mut(&mut layer.resolvers)
+            .insert(name.into(), Arc::new(Resolver::new(value_type, resolver)));
+        self
+    }
+
+    /// Adds a source which is consulted, in the order sources were added, for names
+    /// that are not set on the context itself.
+    pub fn add_source<S>(mut self, source: S) -> Self
+    where
+        S: ContextSource + 'static,
+    {
+        let layer = Arc::make_mut(&mut self.layer);
+        Arc::make_mut(&mut layer.sources).push(Arc::new(source));
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.layers().find_map(|layer| layer.data.get(name))
+    }
+
+    /// Looks up a name in the context data, then in its resolvers, then in its sources
+    /// and finally in the parent context.
+    pub fn fetch(&self, name: &str) -> EvalResult<Cow<'_, Value>> {
+        for layer in self.layers() {
+            if let Some(value) = layer.data.get(name) {
+                return Ok(Cow::Borrowed(value));
+            }
+
+            if let Some(resolver) = layer.resolvers.get(name) {
+                return resolver.resolve(name).map(Cow::Borrowed);
+            }
+
+            if let Some(value) = layer.sources.iter().find_map(|source| source.lookup(name)) {
+                return Ok(Cow::Owned(value));
+            }
+        }
+
+        Err(missing_context(name))
+    }
+
+    /// Same as `fetch()`, with missing names and resolver failures both reported as `None`.
+    pub fn lookup(&self, name: &str) -> Option<Cow<'_, Value>> {
+        self.fetch(name).ok()
+    }
+
+    /// Whether the context provides a value for a name, without running its resolver.
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.layers().any(|layer| {
+            layer.data.contains_key(name)
+                || layer.resolvers.contains_key(name)
+                || layer.sources.iter().any(|source| source.has_key(name))
+        })
+    }
+
+    /// Type of the value stored under a name. Resolvers report their declared type.
+    pub fn value_type(&self, name: &str) -> EvalResult<Type> {
+        for layer in self.layers() {
+            if let Some(value) = layer.data.get(name) {
+                return Ok(value.concrete_type());
+            }
+
+            if let Some(resolver) = layer.resolvers.get(name) {
+                return Ok(resolver.value_type().clone());
+            }
+
+            if let Some(value) = layer.sources.iter().find_map(|source| source.lookup(name)) {
+                return Ok(value.concrete_type());
+            }
+        }
+
+        Err(missing_context(name))
+    }
+
+    /// Accounts for one evaluation step. Expressions call this at the start of `eval()`.
+    pub fn check_budget(&self) -> EvalResult<()> {
+        match &self.budget {
+            Some(budget) => budget.check_step(),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks a value produced during evaluation against the budget's size limit.
+    pub fn check_value_size(&self, value: &Value) -> EvalResult<()> {
+        match &self.budget {
+            Some(budget) => budget.check_value_size(value),
+            None => Ok(()),
+        }
+    }
+}
+
+fn missing_context(name: &str) -> EvalError {
+    EvalError {
+        error_kind: EvalErrorKind::MissingContext {
+            name: name.to_string(),
+        },
+    }
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, V> Extend<(S, V)> for Context
+where
+    S: Into<String>,
+    V: Into<Value>,
+{
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (S, V)>,
+    {
+        let layer = Arc::make_mut(&mut self.layer);
+        let data = Arc::make_mut(&mut layer.data);
+
+        for (name, value) in iter {
+            data.insert(name.into(), value.into());
+        }
+    }
+}
+
+impl<S, V> FromIterator<(S, V)> for Context
+where
+    S: Into<String>,
+    V: Into<Value>,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (S, V)>,
+    {
+        let mut context = Context::new();
+        context.extend(iter);
+        context
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Context {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_map(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Context {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let json = serde_json::Value::deserialize(deserializer)?;
+        Context::from_json(&json).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::ops::*;
+
+    #[test]
+    fn it_mutates_in_place() {
+        let mut context = Context::new().set("userId", 1).set("country", "DE");
+
+        assert_eq!(context.insert("userId", 2), Some(Value::Int(1)));
+        context.insert("roles", vec!["admin"]);
+
+        assert!(context.contains_key("roles"));
+        assert_eq!(
+            context.remove("country"),
+            Some(Value::Str("DE".to_string()))
+        );
+        assert!(!context.contains_key("country"));
+        assert_eq!(context.get("userId"), Some(&Value::Int(2)));
+    }
+
+    #[test]
+    fn it_extends_and_iterates() {
+        let mut context: Context = vec![("a", 1), ("b", 2)].into_iter().collect();
+        context.extend([("b", Value::Float(2.5)), ("c", Value::Bool(true))]);
+
+        let mut entries: Vec<_> = context.iter().collect();
+        entries.sort_by_key(|(name, _)| *name);
+
+        assert_eq!(
+            entries,
+            vec![
+                ("a", &Value::Int(1)),
+                ("b", &Value::Float(2.5)),
+                ("c", &Value::Bool(true))
+            ]
+        );
+    }
+
+    #[test]
+    fn it_layers_child_over_parent() {
+        let global = Context::new().set("plan", "free").set("region", "eu");
+        let mut request = global.child().set("plan", "pro").set("userId", 1);
+
+        assert_eq!(get("plan").eval(&request).unwrap(), str_val("pro"));
+        assert_eq!(get("region").eval(&request).unwrap(), str_val("eu"));
+        assert_eq!(request.iter().count(), 3);
+        assert!(request.contains_key("region"));
+
+        let session = request.child().set("region", "us");
+        let region = Value::Str("us".to_string());
+
+        assert_eq!(session.iter().count(), 3);
+        assert!(session.iter().any(|entry| entry == ("region", &region)));
+
+        request.remove("plan");
+
+        assert_eq!(get("plan").eval(&request).unwrap(), str_val("free"));
+        assert_eq!(request.remove("region"), None);
+        assert!(get("userId").eval(&global).is_err());
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn it_round_trips_through_serde() {
+        let json = json!({"userId": 1, "roles": ["admin", "dev"]});
+        let context: Context = serde_json::from_value(json.clone()).unwrap();
+
+        assert_eq!(context.get("userId"), Some(&Value::Int(1)));
+        assert_eq!(serde_json::to_value(&context).unwrap(), json);
+
+        let nested: Context = serde_json::from_value(json!({"user": {"id": 1}})).unwrap();
+
+        assert_eq!(nested.get("user.id"), Some(&Value::Int(1)));
+    }
+}
@@ -0,0 +1,165 @@
+use crate::expression::eval_type::Type;
+use crate::expression::value::Value;
+use crate::expression::{EvalError, EvalErrorKind, EvalResult};
+use std::error::Error;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+pub type ResolverError = Arc<dyn Error + Send + Sync>;
+
+type ResolverFn = dyn Fn() -> Result<Value, ResolverError> + Send + Sync;
+
+/// Computes a context value the first time it is read and remembers it. Failures are
+/// not remembered, so the next read runs the closure again.
+pub(crate) struct Resolver {
+    value_type: Type,
+    resolve: Box<ResolverFn>,
+    value: OnceLock<Value>,
+}
+
+impl Resolver {
+    pub(crate) fn new<F, E>(value_type: Type, resolve: F) -> Self
+    where
+        F: Fn() -> Result<Value, E> + Send + Sync + 'static,
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        Self {
+            value_type,
+            resolve: Box::new(move || resolve().map_err(|err| Arc::from(err.into()))),
+            value: OnceLock::new(),
+        }
+    }
+
+    pub(crate) fn value_type(&self) -> &Type {
+        &self.value_type
+    }
+
+    pub(crate) fn resolve(&self, name: &str) -> EvalResult<&Value> {
+        if let Some(value) = self.value.get() {
+            return Ok(value);
+        }
+
+        match (self.resolve)() {
+            Ok(value) if value.concrete_type() == self.value_type => {
+                Ok(self.value.get_or_init(|| value))
+            }
+            Ok(value) => Err(EvalError {
+                error_kind: EvalErrorKind::ValueTypeMismatch {
+                    expected: self.value_type.clone(),
+                    actual: value.concrete_type(),
+                },
+            }),
+            Err(err) => Err(EvalError {
+                error_kind: EvalErrorKind::ResolverFailed {
+                    name: name.to_string(),
+                    source: err,
+                },
+            }),
+        }
+    }
+}
+
+impl fmt::Debug for Resolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Resolver")
+            .field("value_type", &self.value_type)
+            .field("value", &self.value.get())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::budget::EvalBudget;
+    use crate::context::Context;
+    use crate::expression::eval_type::Type;
+    use crate::expression::ops::*;
+    use crate::expression::value::Value;
+    use crate::expression::EvalErrorKind;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn it_resolves_lazily_once() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let context = Context::new().set_resolver("recentOrders", Type::Int, move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+            Ok::<_, String>(Value::Int(3))
+        });
+        let expression = gt(get("recentOrders"), int(2));
+
+        assert_eq!(expression.eval_type(&context).unwrap(), Type::Bool);
+        assert_eq!(calls.load(Ordering::Relaxed), 0);
+
+        assert!(expression.eval_bool(&context).unwrap());
+        assert!(expression.eval_bool(&context).unwrap());
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn it_shares_resolved_values_between_clones() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let context = Context::new().set_resolver("recentOrders", Type::Int, move || {
+            counter.fetch_add(1, Ordering::Relaxed);
+            Ok::<_, String>(Value::Int(3))
+        });
+        let expression = gt(get("recentOrders"), int(2));
+
+        assert!(expression.eval_bool(&context.clone()).unwrap());
+        assert!(expression.eval_bool(&context.child()).unwrap());
+        assert!(expression
+            .eval_with_budget(&context, &EvalBudget::new())
+            .unwrap()
+            .as_bool()
+            .unwrap());
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn it_does_not_remember_failures() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let context = Context::new().set_resolver("recentOrders", Type::Int, move || match counter
+            .fetch_add(1, Ordering::Relaxed)
+        {
+            0 => Err("database unavailable"),
+            _ => Ok(Value::Int(3)),
+        });
+
+        assert!(get("recentOrders").eval(&context).is_err());
+        assert_eq!(get("recentOrders").eval(&context).unwrap(), Value::Int(3));
+        assert_eq!(get("recentOrders").eval(&context).unwrap(), Value::Int(3));
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn it_reports_resolver_failures() {
+        let context =
+            Context::new().set_resolver("recentOrders", Type::Int, || Err("database unavailable"));
+
+        let err = get("recentOrders").eval(&context).unwrap_err();
+
+        assert!(matches!(
+            err.kind(),
+            EvalErrorKind::ResolverFailed { name, source }
+                if name == "recentOrders" && source.to_string() == "database unavailable"
+        ));
+    }
+
+    #[test]
+    fn it_checks_resolved_values_against_declared_type() {
+        let context = Context::new().set_resolver("recentOrders", Type::Int, || {
+            Ok::<_, String>(Value::Str("3".to_string()))
+        });
+
+        assert!(matches!(
+            get("recentOrders").eval(&context).unwrap_err().kind(),
+            EvalErrorKind::ValueTypeMismatch {
+                expected: Type::Int,
+                actual: Type::Str
+            }
+        ));
+    }
+}
@@ -0,0 +1,176 @@
+use crate::context::json::json_to_value;
+use crate::context::Context;
+use crate::expression::value::Value;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Provides context values by name to `get` without copying them into a `Context` first.
+///
+/// Paths are the names used by `get`. Sources holding nested data should treat them as
+/// dotted paths, the same way `Context::from_json()` flattens nested objects.
+pub trait ContextSource: Send + Sync {
+    fn lookup(&self, path: &str) -> Option<Value>;
+    /// Whether `lookup()` would find a value, used by `Context::contains_key()`. Sources
+    /// which can answer without building the value should override it.
+    fn has_key(&self, path: &str) -> bool {
+        self.lookup(path).is_some()
+    }
+}
+
+impl fmt::Debug for dyn ContextSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ContextSource")
+    }
+}
+
+impl ContextSource for Context {
+    fn lookup(&self, path: &str) -> Option<Value> {
+        Context::lookup(self, path).map(|value| value.into_owned())
+    }
+
+    fn has_key(&self, path: &str) -> bool {
+        self.contains_key(path)
+    }
+}
+
+/// Looks up dotted paths in nested objects, so `user.id` reads `{"user": {"id": 1}}`.
+/// Values which `Context::from_json()` would reject are treated as missing.
+impl ContextSource for JsonValue {
+    fn lookup(&self, path: &str) -> Option<Value> {
+        let json = match self.get(path) {
+            Some(json) => json,
+            None => path
+                .split('.')
+                .try_fold(self, |json, segment| json.as_object()?.get(segment))?,
+        };
+
+        json_to_value(json, path).ok()
+    }
+}
+
+impl ContextSource for HashMap<String, Value> {
+    fn lookup(&self, path: &str) -> Option<Value> {
+        self.get(path).cloned()
+    }
+
+    fn has_key(&self, path: &str) -> bool {
+        self.contains_key(path)
+    }
+}
+
+impl<T> ContextSource for Arc<T>
+where
+    T: ContextSource + ?Sized,
+{
+    fn lookup(&self, path: &str) -> Option<Value> {
+        self.as_ref().lookup(path)
+    }
+
+    fn has_key(&self, path: &str) -> bool {
+        self.as_ref().has_key(path)
+    }
+}
+
+/// Adapts a closure, typically one reading fields of a domain object.
+impl<F> ContextSource for F
+where
+    F: Fn(&str) -> Option<Value> + Send + Sync,
+{
+    fn lookup(&self, path: &str) -> Option<Value> {
+        self(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::ops::*;
+    use serde_json::json;
+
+    struct User {
+        id: i64,
+        country: String,
+    }
+
+    impl ContextSource for User {
+        fn lookup(&self, path: &str) -> Option<Value> {
+            match path {
+                "userId" => Some(Value::Int(self.id)),
+                "country" => Some(Value::Str(self.country.clone())),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn it_evaluates_against_user_structs() {
+        let user = Arc::new(User {
+            id: 1,
+            country: "DE".to_string(),
+        });
+        let context = Context::new().add_source(user.clone());
+
+        assert!(eq(get("userId"), int(1)).eval_bool(&context).unwrap());
+        assert!(eq(get("country"), str("DE")).eval_bool(&context).unwrap());
+        assert!(get("age").eval(&context).is_err());
+        assert_eq!(user.lookup("userId"), Some(Value::Int(1)));
+        assert!(context.contains_key("country"));
+        assert!(!context.contains_key("age"));
+    }
+
+    #[test]
+    fn it_checks_keys_without_lookups() {
+        struct Reports;
+
+        impl ContextSource for Reports {
+            fn lookup(&self, _path: &str) -> Option<Value> {
+                panic!("reports are expensive to build")
+            }
+
+            fn has_key(&self, path: &str) -> bool {
+                path == "report"
+            }
+        }
+
+        let context = Context::new().add_source(Reports);
+
+        assert!(context.contains_key("report"));
+        assert!(!context.contains_key("summary"));
+    }
+
+    #[test]
+    fn it_evaluates_against_json_documents() {
+        let context = Context::new().add_source(json!({
+            "user": {"id": 1, "roles": ["admin"]},
+            "plan.name": "pro"
+        }));
+
+        assert!(eq(get("user.id"), int(1)).eval_bool(&context).unwrap());
+        assert!(eq(get("user.roles"), str_array(["admin"]))
+            .eval_bool(&context)
+            .unwrap());
+        assert!(eq(get("plan.name"), str("pro"))
+            .eval_bool(&context)
+            .unwrap());
+        assert!(get("user").eval(&context).is_err());
+    }
+
+    #[test]
+    fn it_prefers_context_data_over_sources() {
+        let context = Context::new()
+            .set_int("userId", 1)
+            .add_source(|path: &str| match path {
+                "userId" | "accountId" => Some(Value::Int(2)),
+                _ => None,
+            });
+
+        assert_eq!(get("userId").eval(&context).unwrap(), Value::Int(1));
+        assert_eq!(get("accountId").eval(&context).unwrap(), Value::Int(2));
+        assert_eq!(
+            context.lookup("accountId").unwrap().into_owned(),
+            Value::Int(2)
+        );
+    }
+}
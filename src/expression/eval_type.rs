@@ -2,6 +2,11 @@ use crate::context::Context;
 use crate::expression::{EvalError, EvalErrorKind, EvalResult, Expression};
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
 pub enum Type {
     Bool,
     BoolArray(usize),
@@ -19,22 +24,22 @@ pub fn type_check_all_args_have_same_type(
 ) -> EvalResult<Option<Type>> {
     let args = expression.args();
 
-    if args.len() == 0 {
+    if args.is_empty() {
         return Ok(None);
     }
 
-    let expected_type = args[0].eval_type(&context)?;
+    let expected_type = args[0].eval_type(context)?;
 
     args.into_iter()
         .enumerate()
         .skip(1)
         .find_map(|(position, arg)| {
-            let arg_type = arg.eval_type(&context);
+            let arg_type = arg.eval_type(context);
 
             match arg_type {
                 Ok(arg_type) if arg_type == expected_type => None,
                 Ok(arg_type) => Some(Err(EvalError {
-                    errorKind: EvalErrorKind::TypeMismatch {
+                    error_kind: EvalErrorKind::TypeMismatch {
                         op_json: expression.to_json(),
                         arg_position: position,
                         expected: expected_type.clone(),
@@ -46,3 +51,26 @@ pub fn type_check_all_args_have_same_type(
         })
         .unwrap_or(Ok(Some(expected_type)))
 }
+
+pub fn type_check_all_args_have_type(
+    context: &Context,
+    expression: &dyn Expression,
+    expected_type: Type,
+) -> EvalResult<()> {
+    for (position, arg) in expression.args().into_iter().enumerate() {
+        let arg_type = arg.eval_type(context)?;
+
+        if arg_type != expected_type {
+            return Err(EvalError {
+                error_kind: EvalErrorKind::TypeMismatch {
+                    op_json: expression.to_json(),
+                    arg_position: position,
+                    expected: expected_type,
+                    actual: arg_type,
+                },
+            });
+        }
+    }
+
+    Ok(())
+}
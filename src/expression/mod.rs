@@ -1,21 +1,38 @@
 pub mod eval_type;
+mod normal_form;
 pub mod ops;
+mod optimize;
+mod partial;
+pub mod structural;
+pub mod trace;
+pub mod typed;
 pub mod value;
+pub mod visit;
 
-use crate::context::Context;
+use crate::budget::EvalBudget;
+use crate::context::{prefetch, AsyncContextSource, Context, ResolverError};
 use crate::expression::eval_type::Type;
+use crate::expression::structural::{ExpressionAny, ExpressionClone};
+use crate::expression::trace::Trace;
 use crate::expression::value::Value;
+use crate::render::Template;
 use serde_json::{json, Value as JsonValue};
+use std::borrow::Cow;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
 
 pub type EvalResult<T> = Result<T, EvalError>;
 
-#[derive(Debug)]
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+#[derive(Clone, Debug)]
 pub struct EvalError {
-    errorKind: EvalErrorKind,
+    pub(crate) error_kind: EvalErrorKind,
 }
 
-#[derive(Debug)]
-pub(crate) enum EvalErrorKind {
+#[derive(Clone, Debug)]
+pub enum EvalErrorKind {
     MissingContext {
         name: String,
     },
@@ -29,17 +46,103 @@ pub(crate) enum EvalErrorKind {
         expected: Type,
         actual: Type,
     },
+    StepLimitExceeded {
+        max_steps: u64,
+    },
+    ValueSizeLimitExceeded {
+        max_value_size: usize,
+        actual: usize,
+    },
+    DeadlineExceeded,
+    Cancelled,
+    ResolverFailed {
+        name: String,
+        source: ResolverError,
+    },
+}
+
+impl EvalError {
+    pub fn kind(&self) -> &EvalErrorKind {
+        &self.error_kind
+    }
 }
 
-pub trait Expression {
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error_kind {
+            EvalErrorKind::MissingContext { name } => write!(f, "missing context value {}", name),
+            EvalErrorKind::ValueTypeMismatch { expected, actual } => {
+                write!(f, "expected value of type {:?}, got {:?}", expected, actual)
+            }
+            EvalErrorKind::TypeMismatch {
+                op_json,
+                arg_position,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "expected argument {} of {} to have type {:?}, got {:?}",
+                arg_position, op_json, expected, actual
+            ),
+            EvalErrorKind::StepLimitExceeded { max_steps } => {
+                write!(f, "evaluation exceeded {} steps", max_steps)
+            }
+            EvalErrorKind::ValueSizeLimitExceeded {
+                max_value_size,
+                actual,
+            } => write!(
+                f,
+                "value of size {} exceeds maximum size {}",
+                actual, max_value_size
+            ),
+            EvalErrorKind::DeadlineExceeded => write!(f, "evaluation deadline exceeded"),
+            EvalErrorKind::Cancelled => write!(f, "evaluation cancelled"),
+            EvalErrorKind::ResolverFailed { name, source } => {
+                write!(f, "resolving context value {} failed: {}", name, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+pub trait Expression: ExpressionClone + ExpressionAny + Send + Sync {
     fn eval(&self, context: &Context) -> EvalResult<Value>;
+    /// Same as `eval()`, but literals and context values are borrowed instead of cloned.
+    fn eval_cow<'a>(&'a self, context: &'a Context) -> EvalResult<Cow<'a, Value>> {
+        self.eval(context).map(Cow::Owned)
+    }
+    /// Evaluates with names missing from `context` looked up in `source`. The names an
+    /// expression depends on are fetched concurrently before it is evaluated, except that
+    /// `and` and `or` evaluate their arguments one at a time and stop early as in `eval()`.
+    fn eval_async<'a>(
+        &'a self,
+        context: &'a Context,
+        source: &'a dyn AsyncContextSource,
+    ) -> BoxFuture<'a, EvalResult<Value>> {
+        Box::pin(async move {
+            let context = prefetch(context, source, self.context_dependencies()).await?;
+            self.eval(&context)
+        })
+    }
+    /// Evaluates like `eval()` under `budget`, counting its steps from zero for this
+    /// evaluation only. Unlike `Context::set_budget()`, the context and the budget can be
+    /// reused for any number of evaluations.
+    fn eval_with_budget(&self, context: &Context, budget: &EvalBudget) -> EvalResult<Value> {
+        self.eval(&context.with_budget(budget.restarted()))
+    }
+    /// Evaluates like `eval()` and records every evaluated node. Ops which do not
+    /// override it are recorded without their args.
+    fn eval_traced(&self, context: &Context) -> Trace {
+        Trace::new(self.name(), self.to_json(), self.eval(context), Vec::new())
+    }
     fn eval_bool(&self, context: &Context) -> EvalResult<bool> {
-        let value = self.eval(&context)?;
+        let value = self.eval(context)?;
 
         match value.as_bool() {
             Some(content) => Ok(content),
             None => Err(EvalError {
-                errorKind: EvalErrorKind::ValueTypeMismatch {
+                error_kind: EvalErrorKind::ValueTypeMismatch {
                     expected: Type::Bool,
                     actual: value.concrete_type(),
                 },
@@ -47,12 +150,12 @@ pub trait Expression {
         }
     }
     fn eval_bool_array(&self, context: &Context, size: usize) -> EvalResult<Vec<bool>> {
-        let value = self.eval(&context)?;
+        let value = self.eval(context)?;
 
         match value.as_bool_array(size) {
             Some(content) => Ok(content),
             None => Err(EvalError {
-                errorKind: EvalErrorKind::ValueTypeMismatch {
+                error_kind: EvalErrorKind::ValueTypeMismatch {
                     expected: Type::BoolArray(size),
                     actual: value.concrete_type(),
                 },
@@ -60,12 +163,12 @@ pub trait Expression {
         }
     }
     fn eval_int(&self, context: &Context) -> EvalResult<i64> {
-        let value = self.eval(&context)?;
+        let value = self.eval(context)?;
 
         match value.as_int() {
             Some(content) => Ok(content),
             None => Err(EvalError {
-                errorKind: EvalErrorKind::ValueTypeMismatch {
+                error_kind: EvalErrorKind::ValueTypeMismatch {
                     expected: Type::Int,
                     actual: value.concrete_type(),
                 },
@@ -73,12 +176,12 @@ pub trait Expression {
         }
     }
     fn eval_int_array(&self, context: &Context, size: usize) -> EvalResult<Vec<i64>> {
-        let value = self.eval(&context)?;
+        let value = self.eval(context)?;
 
         match value.as_int_array(size) {
             Some(content) => Ok(content),
             None => Err(EvalError {
-                errorKind: EvalErrorKind::ValueTypeMismatch {
+                error_kind: EvalErrorKind::ValueTypeMismatch {
                     expected: Type::IntArray(size),
                     actual: value.concrete_type(),
                 },
@@ -86,12 +189,12 @@ pub trait Expression {
         }
     }
     fn eval_float(&self, context: &Context) -> EvalResult<f64> {
-        let value = self.eval(&context)?;
+        let value = self.eval(context)?;
 
         match value.as_float() {
             Some(content) => Ok(content),
             None => Err(EvalError {
-                errorKind: EvalErrorKind::ValueTypeMismatch {
+                error_kind: EvalErrorKind::ValueTypeMismatch {
                     expected: Type::Float,
                     actual: value.concrete_type(),
                 },
@@ -99,12 +202,12 @@ pub trait Expression {
         }
     }
     fn eval_float_array(&self, context: &Context, size: usize) -> EvalResult<Vec<f64>> {
-        let value = self.eval(&context)?;
+        let value = self.eval(context)?;
 
         match value.as_float_array(size) {
             Some(content) => Ok(content),
             None => Err(EvalError {
-                errorKind: EvalErrorKind::ValueTypeMismatch {
+                error_kind: EvalErrorKind::ValueTypeMismatch {
                     expected: Type::FloatArray(size),
                     actual: value.concrete_type(),
                 },
@@ -112,12 +215,12 @@ pub trait Expression {
         }
     }
     fn eval_str(&self, context: &Context) -> EvalResult<String> {
-        let value = self.eval(&context)?;
+        let value = self.eval(context)?;
 
         match value.as_str() {
             Some(content) => Ok(content),
             None => Err(EvalError {
-                errorKind: EvalErrorKind::ValueTypeMismatch {
+                error_kind: EvalErrorKind::ValueTypeMismatch {
                     expected: Type::Str,
                     actual: value.concrete_type(),
                 },
@@ -125,12 +228,12 @@ pub trait Expression {
         }
     }
     fn eval_str_array(&self, context: &Context, size: usize) -> EvalResult<Vec<String>> {
-        let value = self.eval(&context)?;
+        let value = self.eval(context)?;
 
         match value.as_str_array(size) {
             Some(content) => Ok(content),
             None => Err(EvalError {
-                errorKind: EvalErrorKind::ValueTypeMismatch {
+                error_kind: EvalErrorKind::ValueTypeMismatch {
                     expected: Type::StrArray(size),
                     actual: value.concrete_type(),
                 },
@@ -140,21 +243,106 @@ pub trait Expression {
     fn eval_type(&self, context: &Context) -> EvalResult<Type>;
     fn context_dependencies(&self) -> Option<Vec<String>>;
     fn name(&self) -> &str;
+    /// Literal value of the node, if the node is a literal.
+    fn as_value(&self) -> Option<&Value> {
+        None
+    }
+    #[allow(clippy::borrowed_box)]
     fn args(&self) -> Vec<&Box<dyn Expression>>;
+    /// Same op with `args` in place of `args()`, used by passes which rewrite trees.
+    /// Returns `None` if the op cannot be rebuilt from them. `fold_with()`, and with it
+    /// `optimize()` and `partially_evaluate()`, then keeps the op and its whole subtree
+    /// as they were, so custom ops with args should implement it.
+    fn with_args(&self, _args: Vec<Box<dyn Expression>>) -> Option<Box<dyn Expression>> {
+        None
+    }
+    /// How `Renderer` phrases the op when no template is set for its name.
+    fn template(&self) -> Option<Template> {
+        None
+    }
     fn to_json(&self) -> JsonValue {
         json!({self.name(): self.args().iter().map(|arg| arg.to_json()).collect::<JsonValue>()})
     }
 }
 
+/// Context dependencies of all arguments, in order and without duplicates.
+pub fn args_context_dependencies(expression: &dyn Expression) -> Option<Vec<String>> {
+    let mut dependencies: Vec<String> = Vec::new();
+
+    for arg in expression.args() {
+        for dependency in arg.context_dependencies().unwrap_or_default() {
+            if !dependencies.contains(&dependency) {
+                dependencies.push(dependency);
+            }
+        }
+    }
+
+    if dependencies.is_empty() {
+        None
+    } else {
+        Some(dependencies)
+    }
+}
+
 impl std::fmt::Debug for dyn Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.to_json())
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Box<dyn Expression> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.to_json().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Box<dyn Expression> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let json = JsonValue::deserialize(deserializer)?;
+        crate::parser::parse_json_value(&json).map_err(D::Error::custom)
+    }
+}
+
 #[cfg(test)]
 pub mod test_utils {
     use super::*;
+    use std::sync::Arc;
+    use std::task::{Poll, Wake, Waker};
+    use std::thread::{self, Thread};
+
+    /// Wakes the thread running `block_on()`.
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    /// Runs a future on the current thread, parking it until the future is woken.
+    pub fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut cx = std::task::Context::from_waker(&waker);
+
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+
+            thread::park();
+        }
+    }
 
     pub fn assert_eval_eq(
         context: &Context,
@@ -162,8 +350,8 @@ pub mod test_utils {
         left_type: Type,
         right: Box<dyn Expression>,
     ) {
-        assert_eq!(left.eval_type(&context).unwrap(), left_type);
-        assert_eq!(left.eval(&context).unwrap(), right.eval(&context).unwrap());
+        assert_eq!(left.eval_type(context).unwrap(), left_type);
+        assert_eq!(left.eval(context).unwrap(), right.eval(context).unwrap());
     }
 
     pub fn assert_eval_type_err(
@@ -172,12 +360,12 @@ pub mod test_utils {
         expected: Type,
         actual: Type,
     ) {
-        let result = expression.eval_type(&context);
+        let result = expression.eval_type(context);
 
         assert!(result.is_err());
         assert!(match result {
             Err(EvalError {
-                errorKind:
+                error_kind:
                     EvalErrorKind::TypeMismatch {
                         op_json: _,
                         arg_position: _,
@@ -189,3 +377,40 @@ pub mod test_utils {
         })
     }
 }
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use super::*;
+    use crate::expression::ops::*;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    #[derive(Serialize, Deserialize)]
+    struct Rule {
+        name: String,
+        condition: Box<dyn Expression>,
+        types: Vec<Type>,
+    }
+
+    #[test]
+    fn it_embeds_expressions_in_structs() {
+        let json = json!({
+            "name": "adults",
+            "condition": {"gt": [{"get": ["age"]}, 17]},
+            "types": ["int", {"strArray": 2}]
+        });
+
+        let rule: Rule = serde_json::from_value(json.clone()).unwrap();
+
+        assert_eq!(&rule.condition, &gt(get("age"), int(17)));
+        assert_eq!(rule.types, vec![Type::Int, Type::StrArray(2)]);
+        assert_eq!(serde_json::to_value(&rule).unwrap(), json);
+    }
+
+    #[test]
+    fn it_reports_parser_errors() {
+        let result = serde_json::from_value::<Box<dyn Expression>>(json!({"unknown": [1]}));
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,514 @@
+use crate::context::Context;
+use crate::expression::eval_type::Type;
+use crate::expression::ops::{and, bool, not, or};
+use crate::expression::value::Value;
+use crate::expression::Expression;
+
+/// Most terms `minimize()` expands an expression into before keeping it as it is.
+const MINIMIZE_MAX_TERMS: usize = 256;
+
+/// Most atoms for which `minimize()` checks every assignment to find redundant terms.
+const MINIMIZE_MAX_ATOMS: usize = 12;
+
+impl dyn Expression + '_ {
+    /// Disjunctive normal form: an `or` of `and`s of atoms and negated atoms, where an
+    /// atom is any op other than `and`, `or` and `not`. Returns `None` if it would have
+    /// more than `max_terms` terms, or if an atom may fail.
+    ///
+    /// An atom may fail unless it reads nothing from the context and both evaluates and
+    /// type checks as a bool. The result evaluates args of `and` and `or` in a new order
+    /// and drops atoms which other terms make redundant, so an atom which fails, such as
+    /// a `get` of a missing value, could make it fail where `self` does not, or the other
+    /// way round.
+    ///
+    /// The result evaluates to the same value as `self` from both `eval()` and
+    /// `eval_type()`, in every context. Under an `EvalBudget` it may count a different
+    /// number of steps.
+    pub fn to_dnf(&self, max_terms: usize) -> Option<Box<dyn Expression>> {
+        NormalForm::new(self, Shape::Disjunctive, max_terms).map(|form| form.to_expression())
+    }
+
+    /// Conjunctive normal form: an `and` of `or`s of atoms and negated atoms. Like
+    /// `to_dnf()`, but the other way round.
+    pub fn to_cnf(&self, max_terms: usize) -> Option<Box<dyn Expression>> {
+        NormalForm::new(self, Shape::Conjunctive, max_terms).map(|form| form.to_expression())
+    }
+
+    /// Smallest of `self` and its disjunctive and conjunctive normal forms, after
+    /// removing terms which other terms cover and atoms which do not change a term's
+    /// result. Returns `self` as it is if an atom may fail, as described for `to_dnf()`.
+    pub fn minimize(&self) -> Box<dyn Expression> {
+        let mut smallest = self.clone_box();
+
+        for shape in [Shape::Disjunctive, Shape::Conjunctive] {
+            if let Some(mut form) = NormalForm::new(self, shape, MINIMIZE_MAX_TERMS) {
+                form.remove_redundant_terms();
+                let candidate = form.to_expression();
+
+                if size(candidate.as_ref()) < size(smallest.as_ref()) {
+                    smallest = candidate;
+                }
+            }
+        }
+
+        smallest
+    }
+}
+
+fn size(expression: &dyn Expression) -> usize {
+    1 + expression
+        .args()
+        .into_iter()
+        .map(|arg| size(arg.as_ref()))
+        .sum::<usize>()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    /// `or` of `and`s.
+    Disjunctive,
+    /// `and` of `or`s.
+    Conjunctive,
+}
+
+impl Shape {
+    /// Name of the op joining the terms.
+    fn outer(&self) -> &'static str {
+        match self {
+            Shape::Disjunctive => "or",
+            Shape::Conjunctive => "and",
+        }
+    }
+}
+
+/// Position of an atom and whether it is negated.
+type Literal = (usize, bool);
+
+/// Sorted literals joined by the inner op of a shape.
+type Term = Vec<Literal>;
+
+struct NormalForm {
+    shape: Shape,
+    atoms: Vec<Box<dyn Expression>>,
+    terms: Vec<Term>,
+}
+
+impl NormalForm {
+    fn new(expression: &dyn Expression, shape: Shape, max_terms: usize) -> Option<Self> {
+        let mut form = Self {
+            shape,
+            atoms: Vec::new(),
+            terms: Vec::new(),
+        };
+
+        form.terms = form.expand(expression, false, max_terms)?;
+        Some(form)
+    }
+
+    /// Terms of `expression`, or of its negation if `negated`.
+    fn expand(
+        &mut self,
+        expression: &dyn Expression,
+        negated: bool,
+        max_terms: usize,
+    ) -> Option<Vec<Term>> {
+        if let Some(content) = expression.as_value().and_then(|value| value.as_bool()) {
+            // One empty term is the identity of the outer op, no terms the one of the inner op.
+            return Some(
+                if (content != negated) == (self.shape == Shape::Disjunctive) {
+                    vec![Vec::new()]
+                } else {
+                    Vec::new()
+                },
+            );
+        }
+
+        let args = expression.args();
+
+        match (expression.name(), args.as_slice()) {
+            ("not", [arg]) if expression.as_value().is_none() => {
+                self.expand(arg.as_ref(), !negated, max_terms)
+            }
+            ("and" | "or", args) if expression.as_value().is_none() => {
+                let op = match (expression.name(), negated) {
+                    ("and", true) => "or",
+                    ("or", true) => "and",
+                    (name, _) => name,
+                };
+                let mut terms = if op == self.shape.outer() {
+                    Vec::new()
+                } else {
+                    vec![Vec::new()]
+                };
+
+                for arg in args {
+                    let arg_terms = self.expand(arg.as_ref(), negated, max_terms)?;
+
+                    terms = if op == self.shape.outer() {
+                        terms.into_iter().chain(arg_terms).collect()
+                    } else {
+                        let mut product = Vec::new();
+
+                        for term in &terms {
+                            for arg_term in &arg_terms {
+                                product.push(term.iter().chain(arg_term).copied().collect());
+                            }
+
+                            if product.len() > max_terms.saturating_mul(2) {
+                                return None;
+                            }
+                        }
+
+                        product
+                    };
+
+                    terms = simplify(terms);
+
+                    if terms.len() > max_terms {
+                        return None;
+                    }
+                }
+
+                Some(terms)
+            }
+            _ => Some(vec![vec![(self.atom(expression)?, negated)]]),
+        }
+    }
+
+    /// Position of `expression` among the atoms, or `None` if it may fail.
+    fn atom(&mut self, expression: &dyn Expression) -> Option<usize> {
+        match self
+            .atoms
+            .iter()
+            .position(|atom| atom.as_ref() == expression)
+        {
+            Some(position) => Some(position),
+            None if is_infallible_bool(expression) => {
+                self.atoms.push(expression.clone_box());
+                Some(self.atoms.len() - 1)
+            }
+            None => None,
+        }
+    }
+
+    /// Drops literals whose removal does not change which assignments of the atoms
+    /// satisfy the form, then terms which the remaining terms cover.
+    fn remove_redundant_terms(&mut self) {
+        if self.atoms.len() > MINIMIZE_MAX_ATOMS {
+            return;
+        }
+
+        let assignments: Vec<u32> = (0..1u32 << self.atoms.len()).collect();
+        // A disjunctive form is true where one of its terms is. A conjunctive form is
+        // false where one of its terms is, which is the same as each term being true
+        // with its literals negated, so both shapes are reduced alike.
+        let covered = |terms: &[Term], term: &Term| {
+            assignments
+                .iter()
+                .filter(|assignment| satisfies(term, **assignment))
+                .all(|assignment| terms.iter().any(|other| satisfies(other, *assignment)))
+        };
+
+        for position in 0..self.terms.len() {
+            let mut literal = 0;
+
+            while literal < self.terms[position].len() {
+                let mut shorter = self.terms[position].clone();
+                shorter.remove(literal);
+
+                if covered(&self.terms, &shorter) {
+                    self.terms[position] = shorter;
+                } else {
+                    literal += 1;
+                }
+            }
+        }
+
+        let terms = std::mem::take(&mut self.terms);
+        self.terms = simplify(terms);
+
+        let mut position = 0;
+
+        while position < self.terms.len() {
+            let term = self.terms.remove(position);
+
+            if !covered(&self.terms, &term) {
+                self.terms.insert(position, term);
+                position += 1;
+            }
+        }
+    }
+
+    fn to_expression(&self) -> Box<dyn Expression> {
+        type Join = fn(Vec<Box<dyn Expression>>) -> Box<dyn Expression>;
+
+        let (outer, inner): (Join, Join) = match self.shape {
+            Shape::Disjunctive => (or, and),
+            Shape::Conjunctive => (and, or),
+        };
+        let identity = self.shape == Shape::Conjunctive;
+
+        let mut terms: Vec<Box<dyn Expression>> = self
+            .terms
+            .iter()
+            .map(|term| {
+                let mut literals: Vec<Box<dyn Expression>> = term
+                    .iter()
+                    .map(|(atom, negated)| {
+                        let atom = self.atoms[*atom].clone();
+
+                        if *negated {
+                            not(atom)
+                        } else {
+                            atom
+                        }
+                    })
+                    .collect();
+
+                match literals.len() {
+                    0 => bool(!identity),
+                    1 => literals.remove(0),
+                    _ => inner(literals),
+                }
+            })
+            .collect();
+
+        match terms.len() {
+            0 => bool(identity),
+            1 => terms.remove(0),
+            _ => outer(terms),
+        }
+    }
+}
+
+fn satisfies(term: &Term, assignment: u32) -> bool {
+    term.iter()
+        .all(|(atom, negated)| ((assignment >> atom) & 1 == 1) != *negated)
+}
+
+/// Sorts and deduplicates the literals of each term, then drops terms with an atom
+/// and its negation, duplicate terms and terms which contain another term.
+fn simplify(terms: Vec<Term>) -> Vec<Term> {
+    let mut simplified: Vec<Term> = Vec::new();
+
+    for mut term in terms {
+        term.sort_unstable();
+        term.dedup();
+
+        let constant = term.windows(2).any(|pair| pair[0].0 == pair[1].0);
+
+        if constant || simplified.iter().any(|other| covers(other, &term)) {
+            continue;
+        }
+
+        simplified.retain(|other| !covers(&term, other));
+        simplified.push(term);
+    }
+
+    simplified
+}
+
+/// Whether every literal of `term` is in `other`.
+fn covers(term: &Term, other: &Term) -> bool {
+    term.iter().all(|literal| other.contains(literal))
+}
+
+/// Whether `expression` evaluates and type checks as a bool in every context.
+fn is_infallible_bool(expression: &dyn Expression) -> bool {
+    let context = Context::new();
+
+    expression.context_dependencies().is_none()
+        && matches!(expression.eval_type(&context), Ok(Type::Bool))
+        && matches!(expression.eval(&context), Ok(Value::Bool(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::expression::ops::*;
+    use crate::expression::Expression;
+    use serde_json::json;
+
+    fn assert_same_results(left: &dyn Expression, right: &dyn Expression, context: &Context) {
+        assert_eq!(
+            format!("{:?}", left.eval(context)),
+            format!("{:?}", right.eval(context)),
+            "{} and {} evaluate differently for {}",
+            left.to_json(),
+            right.to_json(),
+            context.to_json()
+        );
+        assert_eq!(
+            format!("{:?}", left.eval_type(context)),
+            format!("{:?}", right.eval_type(context)),
+            "{} and {} type check differently for {}",
+            left.to_json(),
+            right.to_json(),
+            context.to_json()
+        );
+    }
+
+    /// Atoms which cannot fail, named by a letter whose bit in `assignment` is their value.
+    fn flags(assignment: u32) -> impl Fn(&str) -> Box<dyn Expression> {
+        move |name| {
+            let bit = name.bytes().next().unwrap() - b'a';
+            let content = (assignment >> bit) & 1 == 1;
+
+            eq(str(name), str(if content { name } else { "" }))
+        }
+    }
+
+    #[test]
+    fn it_converts_to_dnf() {
+        for assignment in 0..16 {
+            let flag = flags(assignment);
+            let expression = and([
+                or([flag("a"), flag("d")]),
+                not(and([flag("b"), not(flag("c"))])),
+            ]);
+
+            let dnf = expression.to_dnf(16).unwrap();
+
+            assert_eq!(
+                &dnf,
+                &or([
+                    and([flag("a"), not(flag("b"))]),
+                    and([flag("a"), flag("c")]),
+                    and([flag("d"), not(flag("b"))]),
+                    and([flag("d"), flag("c")]),
+                ])
+            );
+            assert_same_results(expression.as_ref(), dnf.as_ref(), &Context::new());
+            assert!(expression.to_dnf(3).is_none());
+        }
+    }
+
+    #[test]
+    fn it_converts_to_cnf() {
+        for assignment in 0..8 {
+            let flag = flags(assignment);
+            let expression = or([and([flag("a"), flag("b")]), not(or([flag("c"), flag("a")]))]);
+
+            let cnf = expression.to_cnf(16).unwrap();
+
+            assert_eq!(
+                &cnf,
+                &and([
+                    or([flag("a"), not(flag("c"))]),
+                    or([flag("b"), not(flag("c"))]),
+                    or([not(flag("a")), flag("b")]),
+                ])
+            );
+            assert_same_results(expression.as_ref(), cnf.as_ref(), &Context::new());
+        }
+    }
+
+    #[test]
+    fn it_serializes_normal_forms() {
+        let flag = flags(1);
+        let expression = not(or([flag("a"), flag("b")]));
+
+        assert_eq!(
+            expression.to_dnf(16).unwrap().to_json(),
+            json!({"and": [
+                {"not": [{"eq": ["a", "a"]}]},
+                {"not": [{"eq": ["b", ""]}]}
+            ]})
+        );
+    }
+
+    #[test]
+    fn it_guards_the_size() {
+        let flag = |name: String| eq(str(name.as_str()), str(name.as_str()));
+        let expression = and((0..8)
+            .map(|position| {
+                or([
+                    flag(format!("a{}", position)),
+                    flag(format!("b{}", position)),
+                ])
+            })
+            .collect::<Vec<_>>());
+
+        assert!(expression.to_dnf(255).is_none());
+        assert!(expression.to_dnf(256).is_some());
+        assert_eq!(&expression.to_cnf(8).unwrap(), &expression);
+        assert_eq!(&expression.minimize(), &expression);
+    }
+
+    #[test]
+    fn it_minimizes() {
+        for assignment in 0..8 {
+            let flag = flags(assignment);
+            let expressions = [
+                (
+                    or([
+                        and([flag("a"), flag("b")]),
+                        and([flag("a"), not(flag("b"))]),
+                        and([flag("a"), flag("c")]),
+                    ]),
+                    flag("a"),
+                ),
+                (
+                    and([
+                        flag("a"),
+                        or([flag("b"), flag("c")]),
+                        or([flag("a"), flag("b")]),
+                    ]),
+                    and([flag("a"), or([flag("b"), flag("c")])]),
+                ),
+                (
+                    or([flag("a"), and([not(flag("a")), flag("b")])]),
+                    or([flag("a"), flag("b")]),
+                ),
+                (or([flag("a"), not(flag("a"))]), bool(true)),
+                (and([flag("c"), flag("b"), not(flag("c"))]), bool(false)),
+            ];
+
+            for (expression, minimized) in expressions {
+                assert_eq!(&expression.minimize(), &minimized);
+                assert_same_results(expression.as_ref(), minimized.as_ref(), &Context::new());
+            }
+        }
+    }
+
+    #[test]
+    fn it_keeps_expressions_whose_atoms_may_fail() {
+        let flag = flags(1);
+        let expressions = [
+            or([get("a"), not(get("a"))]),
+            and([get("c"), gt(get("age"), int(3)), not(get("c"))]),
+            or([and([get("a"), get("b")]), and([get("a"), not(get("b"))])]),
+            or([flag("a"), eq(get("b"), bool(true))]),
+            and([flag("a"), int(1), not(flag("a"))]),
+        ];
+
+        for expression in expressions {
+            assert!(expression.to_dnf(16).is_none());
+            assert!(expression.to_cnf(16).is_none());
+
+            let minimized = expression.minimize();
+            assert_eq!(&minimized, &expression);
+
+            for context in [
+                Context::new(),
+                Context::new().set_int("a", 1).set_bool("b", true),
+                Context::new().set_bool("a", true).set_int("b", 1),
+                Context::new()
+                    .set_bool("a", true)
+                    .set_bool("b", true)
+                    .set_bool("c", false)
+                    .set_int("age", 4),
+            ] {
+                assert_same_results(expression.as_ref(), minimized.as_ref(), &context);
+            }
+        }
+    }
+
+    #[test]
+    fn it_does_not_overflow_the_size_guard() {
+        let flag = flags(5);
+        let expression = and([or([flag("a"), flag("b")]), or([flag("c"), flag("d")])]);
+
+        assert!(expression.to_dnf(usize::MAX).is_some());
+    }
+}
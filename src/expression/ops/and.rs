@@ -0,0 +1,143 @@
+use crate::context::{AsyncContextSource, Context, FetchedSource};
+use crate::expression::eval_type::{type_check_all_args_have_type, Type};
+use crate::expression::trace::{trace_args, Trace};
+use crate::expression::value::Value;
+use crate::expression::{
+    args_context_dependencies, BoxFuture, EvalError, EvalErrorKind, EvalResult, Expression,
+};
+use crate::render::Template;
+
+pub fn and<A>(args: A) -> Box<dyn Expression>
+where
+    A: Into<Vec<Box<dyn Expression>>>,
+{
+    Box::new(And { args: args.into() })
+}
+
+#[derive(Clone)]
+pub struct And {
+    args: Vec<Box<dyn Expression>>,
+}
+
+impl Expression for And {
+    fn eval(&self, context: &Context) -> EvalResult<Value> {
+        context.check_budget()?;
+
+        for arg in &self.args {
+            if !expect_bool(&*arg.eval_cow(context)?)? {
+                return Ok(Value::Bool(false));
+            }
+        }
+
+        Ok(Value::Bool(true))
+    }
+
+    fn eval_async<'a>(
+        &'a self,
+        context: &'a Context,
+        source: &'a dyn AsyncContextSource,
+    ) -> BoxFuture<'a, EvalResult<Value>> {
+        Box::pin(async move {
+            context.check_budget()?;
+            let source = FetchedSource::new(source);
+
+            for arg in &self.args {
+                if !expect_bool(&arg.eval_async(context, &source).await?)? {
+                    return Ok(Value::Bool(false));
+                }
+            }
+
+            Ok(Value::Bool(true))
+        })
+    }
+
+    fn eval_traced(&self, context: &Context) -> Trace {
+        trace_args(
+            self,
+            context,
+            |value| Ok((!expect_bool(value)?).then_some(Value::Bool(false))),
+            |_| Ok(Value::Bool(true)),
+        )
+    }
+
+    fn eval_type(&self, context: &Context) -> EvalResult<Type> {
+        type_check_all_args_have_type(context, self, Type::Bool)?;
+        Ok(Type::Bool)
+    }
+
+    fn context_dependencies(&self) -> Option<Vec<String>> {
+        args_context_dependencies(self)
+    }
+
+    fn name(&self) -> &str {
+        "and"
+    }
+
+    fn args(&self) -> Vec<&Box<dyn Expression>> {
+        self.args.iter().collect()
+    }
+
+    fn with_args(&self, args: Vec<Box<dyn Expression>>) -> Option<Box<dyn Expression>> {
+        Some(and(args))
+    }
+
+    fn template(&self) -> Option<Template> {
+        Some(Template::join(" and "))
+    }
+}
+
+pub(crate) fn expect_bool(value: &Value) -> EvalResult<bool> {
+    match value.as_bool() {
+        Some(content) => Ok(content),
+        None => Err(EvalError {
+            error_kind: EvalErrorKind::ValueTypeMismatch {
+                expected: Type::Bool,
+                actual: value.concrete_type(),
+            },
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::ops::*;
+    use crate::expression::test_utils::*;
+
+    #[test]
+    fn it_combines_bools() {
+        let context = Context::new();
+
+        assert_eval_eq(
+            &context,
+            and([bool(true), bool(true)]),
+            Type::Bool,
+            bool(true),
+        );
+        assert_eval_eq(
+            &context,
+            and([bool(true), bool(false)]),
+            Type::Bool,
+            bool(false),
+        );
+        assert_eval_eq(&context, and([bool(true)]), Type::Bool, bool(true));
+    }
+
+    #[test]
+    fn it_short_circuits() {
+        let context = Context::new();
+
+        assert_eq!(
+            and([bool(false), get("missing")]).eval(&context).unwrap(),
+            Value::Bool(false)
+        );
+        assert!(and([bool(true), get("missing")]).eval(&context).is_err());
+    }
+
+    #[test]
+    fn it_does_not_combine_other_types() {
+        let context = Context::new();
+
+        assert_eval_type_err(&context, and([bool(true), int(1)]), Type::Bool, Type::Int);
+    }
+}
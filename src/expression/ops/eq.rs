@@ -1,12 +1,15 @@
 use crate::context::Context;
 use crate::expression::eval_type::{type_check_all_args_have_same_type, Type};
+use crate::expression::trace::{trace_args, Trace};
 use crate::expression::value::Value;
-use crate::expression::{EvalResult, Expression};
+use crate::expression::{args_context_dependencies, EvalResult, Expression};
+use crate::render::Template;
 
 pub fn eq(left: Box<dyn Expression>, right: Box<dyn Expression>) -> Box<dyn Expression> {
     Box::new(Eq { left, right })
 }
 
+#[derive(Clone)]
 pub struct Eq {
     left: Box<dyn Expression>,
     right: Box<dyn Expression>,
@@ -14,23 +17,34 @@ pub struct Eq {
 
 impl Expression for Eq {
     fn eval(&self, context: &Context) -> EvalResult<Value> {
-        let lval = self.left.eval(context)?;
-        let rval = self.right.eval(context)?;
+        context.check_budget()?;
 
-        if lval == rval {
+        let lval = self.left.eval_cow(context)?;
+        let rval = self.right.eval_cow(context)?;
+
+        if *lval == *rval {
             Ok(Value::Bool(true))
         } else {
             Ok(Value::Bool(false))
         }
     }
 
+    fn eval_traced(&self, context: &Context) -> Trace {
+        trace_args(
+            self,
+            context,
+            |_| Ok(None),
+            |values| Ok(Value::Bool(values[0] == values[1])),
+        )
+    }
+
     fn eval_type(&self, context: &Context) -> EvalResult<Type> {
-        type_check_all_args_have_same_type(&context, self)?;
+        type_check_all_args_have_same_type(context, self)?;
         Ok(Type::Bool)
     }
 
     fn context_dependencies(&self) -> Option<Vec<String>> {
-        None
+        args_context_dependencies(self)
     }
 
     fn name(&self) -> &str {
@@ -40,6 +54,15 @@ impl Expression for Eq {
     fn args(&self) -> Vec<&Box<dyn Expression>> {
         vec![&self.left, &self.right]
     }
+
+    fn with_args(&self, args: Vec<Box<dyn Expression>>) -> Option<Box<dyn Expression>> {
+        let [left, right]: [Box<dyn Expression>; 2] = args.try_into().ok()?;
+        Some(eq(left, right))
+    }
+
+    fn template(&self) -> Option<Template> {
+        Some(Template::pattern("{0} is {1}"))
+    }
 }
 
 #[cfg(test)]
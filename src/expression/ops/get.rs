@@ -1,7 +1,9 @@
 use crate::context::Context;
 use crate::expression::eval_type::Type;
 use crate::expression::value::{str, Value};
-use crate::expression::{EvalError, EvalErrorKind, EvalResult, Expression};
+use crate::expression::{EvalResult, Expression};
+use crate::render::Template;
+use std::borrow::Cow;
 
 pub fn get<S>(name: S) -> Box<dyn Expression>
 where
@@ -15,6 +17,19 @@ where
     })
 }
 
+/// Name read by `expression` if it is a `get` of a literal name.
+pub(crate) fn get_name(expression: &dyn Expression) -> Option<String> {
+    if expression.name() != "get" || expression.as_value().is_some() {
+        return None;
+    }
+
+    match expression.args().as_slice() {
+        [name] => name.as_value()?.as_str(),
+        _ => None,
+    }
+}
+
+#[derive(Clone)]
 pub struct Get {
     // Get stores its argument as a regular `String` because its `eval_type()` depends
     // on fetching from `Context` by that name. This avoids having to `eval()` inside `eval_type()`.
@@ -23,27 +38,28 @@ pub struct Get {
     name_arg: Box<dyn Expression>,
 }
 
+impl Get {
+    /// Name of the context value the op reads.
+    pub(crate) fn context_name(&self) -> &str {
+        &self.name
+    }
+}
+
 impl Expression for Get {
     fn eval(&self, context: &Context) -> EvalResult<Value> {
-        match context.get(&self.name) {
-            Some(value) => Ok(value.clone()),
-            None => Err(EvalError {
-                errorKind: EvalErrorKind::MissingContext {
-                    name: self.name.clone(),
-                },
-            }),
-        }
+        self.eval_cow(context).map(Cow::into_owned)
+    }
+
+    fn eval_cow<'a>(&'a self, context: &'a Context) -> EvalResult<Cow<'a, Value>> {
+        context.check_budget()?;
+
+        let value = context.fetch(&self.name)?;
+        context.check_value_size(&value)?;
+        Ok(value)
     }
 
     fn eval_type(&self, context: &Context) -> EvalResult<Type> {
-        match context.get(&self.name) {
-            Some(value) => value.eval_type(&context),
-            None => Err(EvalError {
-                errorKind: EvalErrorKind::MissingContext {
-                    name: self.name.clone(),
-                },
-            }),
-        }
+        context.value_type(&self.name)
     }
 
     fn context_dependencies(&self) -> Option<Vec<String>> {
@@ -57,4 +73,49 @@ impl Expression for Get {
     fn args(&self) -> Vec<&Box<dyn Expression>> {
         vec![&self.name_arg]
     }
+
+    fn with_args(&self, args: Vec<Box<dyn Expression>>) -> Option<Box<dyn Expression>> {
+        match args.as_slice() {
+            [name] => Some(get(name.as_value()?.as_str()?)),
+            _ => None,
+        }
+    }
+
+    fn template(&self) -> Option<Template> {
+        Some(Template::pattern("{0}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::ops::*;
+
+    #[test]
+    fn it_borrows_context_values() {
+        let context = Context::new().set_str_array("roles", ["admin".to_string()]);
+        let expression = get("roles");
+        let literal = str_array(["admin"]);
+
+        let value = expression.eval_cow(&context).unwrap();
+
+        assert!(
+            matches!(value, Cow::Borrowed(content) if std::ptr::eq(content, context.get("roles").unwrap()))
+        );
+        assert!(matches!(
+            literal.eval_cow(&context).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        assert!(eq(get("roles"), literal).eval_bool(&context).unwrap());
+    }
+
+    #[test]
+    fn it_owns_values_from_sources() {
+        let context = Context::new().add_source(|_: &str| Some(Value::Int(1)));
+
+        assert!(matches!(
+            get("userId").eval_cow(&context).unwrap(),
+            Cow::Owned(Value::Int(1))
+        ));
+    }
 }
@@ -1,11 +1,23 @@
+mod and;
 mod eq;
 mod get;
 mod gt;
+mod not;
+mod or;
 
 pub use crate::expression::value::{
     bool, bool_array, bool_array_val, bool_val, float, float_array, float_array_val, float_val,
     int, int_array, int_array_val, int_val, str, str_array, str_array_val, str_val,
 };
+pub use and::and;
+pub(crate) use and::{expect_bool, And};
 pub use eq::eq;
+pub(crate) use eq::Eq;
 pub use get::get;
+pub(crate) use get::{get_name, Get};
 pub use gt::gt;
+pub(crate) use gt::Gt;
+pub use not::not;
+pub(crate) use not::Not;
+pub use or::or;
+pub(crate) use or::Or;
@@ -0,0 +1,94 @@
+use crate::context::{AsyncContextSource, Context};
+use crate::expression::eval_type::{type_check_all_args_have_type, Type};
+use crate::expression::ops::and::expect_bool;
+use crate::expression::trace::{trace_args, Trace};
+use crate::expression::value::Value;
+use crate::expression::{args_context_dependencies, BoxFuture, EvalResult, Expression};
+use crate::render::Template;
+
+pub fn not(arg: Box<dyn Expression>) -> Box<dyn Expression> {
+    Box::new(Not { arg })
+}
+
+#[derive(Clone)]
+pub struct Not {
+    arg: Box<dyn Expression>,
+}
+
+impl Expression for Not {
+    fn eval(&self, context: &Context) -> EvalResult<Value> {
+        context.check_budget()?;
+
+        Ok(Value::Bool(!expect_bool(&*self.arg.eval_cow(context)?)?))
+    }
+
+    fn eval_async<'a>(
+        &'a self,
+        context: &'a Context,
+        source: &'a dyn AsyncContextSource,
+    ) -> BoxFuture<'a, EvalResult<Value>> {
+        Box::pin(async move {
+            context.check_budget()?;
+
+            let value = self.arg.eval_async(context, source).await?;
+            Ok(Value::Bool(!expect_bool(&value)?))
+        })
+    }
+
+    fn eval_traced(&self, context: &Context) -> Trace {
+        trace_args(
+            self,
+            context,
+            |value| Ok(Some(Value::Bool(!expect_bool(value)?))),
+            |_| unreachable!("not finishes after its only arg"),
+        )
+    }
+
+    fn eval_type(&self, context: &Context) -> EvalResult<Type> {
+        type_check_all_args_have_type(context, self, Type::Bool)?;
+        Ok(Type::Bool)
+    }
+
+    fn context_dependencies(&self) -> Option<Vec<String>> {
+        args_context_dependencies(self)
+    }
+
+    fn name(&self) -> &str {
+        "not"
+    }
+
+    fn args(&self) -> Vec<&Box<dyn Expression>> {
+        vec![&self.arg]
+    }
+
+    fn with_args(&self, args: Vec<Box<dyn Expression>>) -> Option<Box<dyn Expression>> {
+        let [arg]: [Box<dyn Expression>; 1] = args.try_into().ok()?;
+        Some(not(arg))
+    }
+
+    fn template(&self) -> Option<Template> {
+        Some(Template::pattern("it is not the case that {0}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::ops::*;
+    use crate::expression::test_utils::*;
+
+    #[test]
+    fn it_negates_bools() {
+        let context = Context::new();
+
+        assert_eval_eq(&context, not(bool(true)), Type::Bool, bool(false));
+        assert_eval_eq(&context, not(bool(false)), Type::Bool, bool(true));
+    }
+
+    #[test]
+    fn it_does_not_negate_other_types() {
+        let context = Context::new();
+
+        assert_eval_type_err(&context, not(int(1)), Type::Bool, Type::Int);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::context::{AsyncContextSource, Context, FetchedSource};
+use crate::expression::eval_type::{type_check_all_args_have_type, Type};
+use crate::expression::ops::and::expect_bool;
+use crate::expression::trace::{trace_args, Trace};
+use crate::expression::value::Value;
+use crate::expression::{args_context_dependencies, BoxFuture, EvalResult, Expression};
+use crate::render::Template;
+
+pub fn or<A>(args: A) -> Box<dyn Expression>
+where
+    A: Into<Vec<Box<dyn Expression>>>,
+{
+    Box::new(Or { args: args.into() })
+}
+
+#[derive(Clone)]
+pub struct Or {
+    args: Vec<Box<dyn Expression>>,
+}
+
+impl Expression for Or {
+    fn eval(&self, context: &Context) -> EvalResult<Value> {
+        context.check_budget()?;
+
+        for arg in &self.args {
+            if expect_bool(&*arg.eval_cow(context)?)? {
+                return Ok(Value::Bool(true));
+            }
+        }
+
+        Ok(Value::Bool(false))
+    }
+
+    fn eval_async<'a>(
+        &'a self,
+        context: &'a Context,
+        source: &'a dyn AsyncContextSource,
+    ) -> BoxFuture<'a, EvalResult<Value>> {
+        Box::pin(async move {
+            context.check_budget()?;
+            let source = FetchedSource::new(source);
+
+            for arg in &self.args {
+                if expect_bool(&arg.eval_async(context, &source).await?)? {
+                    return Ok(Value::Bool(true));
+                }
+            }
+
+            Ok(Value::Bool(false))
+        })
+    }
+
+    fn eval_traced(&self, context: &Context) -> Trace {
+        trace_args(
+            self,
+            context,
+            |value| Ok(expect_bool(value)?.then_some(Value::Bool(true))),
+            |_| Ok(Value::Bool(false)),
+        )
+    }
+
+    fn eval_type(&self, context: &Context) -> EvalResult<Type> {
+        type_check_all_args_have_type(context, self, Type::Bool)?;
+        Ok(Type::Bool)
+    }
+
+    fn context_dependencies(&self) -> Option<Vec<String>> {
+        args_context_dependencies(self)
+    }
+
+    fn name(&self) -> &str {
+        "or"
+    }
+
+    fn args(&self) -> Vec<&Box<dyn Expression>> {
+        self.args.iter().collect()
+    }
+
+    fn with_args(&self, args: Vec<Box<dyn Expression>>) -> Option<Box<dyn Expression>> {
+        Some(or(args))
+    }
+
+    fn template(&self) -> Option<Template> {
+        Some(Template::join(" or "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::ops::*;
+    use crate::expression::test_utils::*;
+
+    #[test]
+    fn it_combines_bools() {
+        let context = Context::new();
+
+        assert_eval_eq(
+            &context,
+            or([bool(false), bool(true)]),
+            Type::Bool,
+            bool(true),
+        );
+        assert_eval_eq(
+            &context,
+            or([bool(false), bool(false)]),
+            Type::Bool,
+            bool(false),
+        );
+        assert_eval_eq(&context, or([bool(false)]), Type::Bool, bool(false));
+    }
+
+    #[test]
+    fn it_short_circuits() {
+        let context = Context::new();
+
+        assert_eq!(
+            or([bool(true), get("missing")]).eval(&context).unwrap(),
+            Value::Bool(true)
+        );
+        assert!(or([bool(false), get("missing")]).eval(&context).is_err());
+    }
+
+    #[test]
+    fn it_does_not_combine_other_types() {
+        let context = Context::new();
+
+        assert_eval_type_err(&context, or([bool(true), str("a")]), Type::Bool, Type::Str);
+    }
+}
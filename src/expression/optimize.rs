@@ -0,0 +1,339 @@
+use crate::context::{AsyncContextSource, Context};
+use crate::expression::eval_type::Type;
+use crate::expression::trace::Trace;
+use crate::expression::value::Value;
+use crate::expression::visit::Folder;
+use crate::expression::{BoxFuture, EvalResult, Expression};
+use crate::render::Template;
+use serde_json::Value as JsonValue;
+use std::borrow::Cow;
+
+impl dyn Expression + '_ {
+    /// Equivalent expression which does less work per evaluation.
+    ///
+    /// Subtrees without `context_dependencies()` are folded into literals, `true` is
+    /// dropped from `and`, `false` from `or`, and double negations are removed where
+    /// the operand is known to be a bool. For every context the result evaluates to the
+    /// same value, or fails with the same error, from both `eval()` and `eval_type()`.
+    ///
+    /// To keep that exact, the result holds on to `self`. `eval_type()` checks `self`,
+    /// since a `TypeMismatch` reports the op JSON and arg position as they are there, and
+    /// evaluations under an `EvalBudget` evaluate `self`, so that the same steps are
+    /// counted and the same literal sizes checked. An expression parsed from the JSON of
+    /// the result has neither guarantee.
+    pub fn optimize(&self) -> Box<dyn Expression> {
+        let optimized = self.fold_with(&mut Optimizer);
+
+        if optimized.as_ref() == self {
+            return self.clone_box();
+        }
+
+        Box::new(Optimized {
+            optimized,
+            original: self.clone_box(),
+        })
+    }
+}
+
+struct Optimizer;
+
+impl Folder for Optimizer {
+    fn fold(&mut self, expression: Box<dyn Expression>) -> Box<dyn Expression> {
+        if let Some(folded) = fold_constant(expression.as_ref()) {
+            return folded;
+        }
+
+        match expression.name() {
+            "and" => drop_literal_args(expression, true),
+            "or" => drop_literal_args(expression, false),
+            "not" => remove_double_negation(expression),
+            _ => expression,
+        }
+    }
+}
+
+/// Result of `optimize()`, which looks like the optimized tree and falls back to the
+/// original where the optimized one could report something else.
+#[derive(Clone)]
+struct Optimized {
+    optimized: Box<dyn Expression>,
+    original: Box<dyn Expression>,
+}
+
+impl Optimized {
+    fn evaluated(&self, context: &Context) -> &dyn Expression {
+        if context.has_budget() {
+            self.original.as_ref()
+        } else {
+            self.optimized.as_ref()
+        }
+    }
+}
+
+impl Expression for Optimized {
+    fn eval(&self, context: &Context) -> EvalResult<Value> {
+        self.evaluated(context).eval(context)
+    }
+
+    fn eval_cow<'a>(&'a self, context: &'a Context) -> EvalResult<Cow<'a, Value>> {
+        self.evaluated(context).eval_cow(context)
+    }
+
+    fn eval_async<'a>(
+        &'a self,
+        context: &'a Context,
+        source: &'a dyn AsyncContextSource,
+    ) -> BoxFuture<'a, EvalResult<Value>> {
+        self.evaluated(context).eval_async(context, source)
+    }
+
+    fn eval_traced(&self, context: &Context) -> Trace {
+        self.evaluated(context).eval_traced(context)
+    }
+
+    fn eval_type(&self, context: &Context) -> EvalResult<Type> {
+        self.original.eval_type(context)
+    }
+
+    fn context_dependencies(&self) -> Option<Vec<String>> {
+        self.optimized.context_dependencies()
+    }
+
+    fn name(&self) -> &str {
+        self.optimized.name()
+    }
+
+    fn as_value(&self) -> Option<&Value> {
+        self.optimized.as_value()
+    }
+
+    fn args(&self) -> Vec<&Box<dyn Expression>> {
+        self.optimized.args()
+    }
+
+    fn with_args(&self, args: Vec<Box<dyn Expression>>) -> Option<Box<dyn Expression>> {
+        self.optimized.with_args(args)
+    }
+
+    fn template(&self) -> Option<Template> {
+        self.optimized.template()
+    }
+
+    fn to_json(&self) -> JsonValue {
+        self.optimized.to_json()
+    }
+}
+
+/// Literal with the value of `expression`, if it does not read the context and both
+/// evaluates and type checks successfully.
+fn fold_constant(expression: &dyn Expression) -> Option<Box<dyn Expression>> {
+    if expression.as_value().is_some() || expression.context_dependencies().is_some() {
+        return None;
+    }
+
+    let context = Context::new();
+    let value_type = expression.eval_type(&context).ok()?;
+    let value = expression.eval(&context).ok()?;
+
+    if value.concrete_type() == value_type {
+        Some(Box::new(value))
+    } else {
+        None
+    }
+}
+
+/// Whether `expression` type checks as a bool or fails to type check, whichever type
+/// the values it reads from the context have.
+pub(super) fn is_bool_op(expression: &dyn Expression) -> bool {
+    let names = expression.context_dependencies().unwrap_or_default();
+    let every_type = [
+        Value::Bool(false),
+        Value::Int(0),
+        Value::Float(0.0),
+        Value::Str(String::new()),
+        Value::BoolArray(Vec::new()),
+        Value::IntArray(Vec::new()),
+        Value::FloatArray(Vec::new()),
+        Value::StrArray(Vec::new()),
+    ];
+
+    every_type.into_iter().all(|value| {
+        let context = names.iter().fold(Context::new(), |context, name| {
+            context.set(name, value.clone())
+        });
+
+        !matches!(expression.eval_type(&context), Ok(value_type) if value_type != Type::Bool)
+    })
+}
+
+/// Drops literal `identity` args, which never change the outcome of `and` (`true`) or
+/// `or` (`false`), and unwraps the op when a single bool arg remains.
+fn drop_literal_args(expression: Box<dyn Expression>, identity: bool) -> Box<dyn Expression> {
+    let is_identity = |arg: &dyn Expression| arg.as_value() == Some(&Value::Bool(identity));
+
+    if !expression
+        .args()
+        .into_iter()
+        .any(|arg| is_identity(arg.as_ref()))
+    {
+        return unwrap_single_arg(expression);
+    }
+
+    let args: Vec<Box<dyn Expression>> = expression
+        .args()
+        .into_iter()
+        .filter(|arg| !is_identity(arg.as_ref()))
+        .cloned()
+        .collect();
+
+    match expression.with_args(args) {
+        Some(rebuilt) => unwrap_single_arg(rebuilt),
+        None => expression,
+    }
+}
+
+pub(super) fn unwrap_single_arg(expression: Box<dyn Expression>) -> Box<dyn Expression> {
+    match expression.args().as_slice() {
+        [arg] if is_bool_op(arg.as_ref()) => (*arg).clone(),
+        _ => expression,
+    }
+}
+
+pub(super) fn remove_double_negation(expression: Box<dyn Expression>) -> Box<dyn Expression> {
+    let inner = match expression.args().as_slice() {
+        [arg] if arg.name() == "not" && arg.as_value().is_none() => arg.args(),
+        _ => return expression,
+    };
+
+    match inner.as_slice() {
+        [arg] if is_bool_op(arg.as_ref()) => (*arg).clone(),
+        _ => expression,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::expression::ops::*;
+    use crate::expression::Expression;
+    use crate::parser::parse;
+    use crate::EvalBudget;
+
+    fn assert_optimizes(expression: Box<dyn Expression>, expected: Box<dyn Expression>) {
+        assert_eq!(&expression.optimize(), &expected);
+    }
+
+    #[test]
+    fn it_folds_constant_subtrees() {
+        assert_optimizes(
+            parse(r#"{"and": [{"eq": [1, 1]}, {"gt": [{"get": ["age"]}, {"not": [false]}]}]}"#)
+                .unwrap(),
+            gt(get("age"), bool(true)),
+        );
+        assert_optimizes(eq(int_array([1, 2]), int_array([1, 2])), bool(true));
+        assert_optimizes(get("age"), get("age"));
+    }
+
+    #[test]
+    fn it_applies_boolean_identities() {
+        assert_optimizes(
+            not(not(eq(get("userId"), int(1)))),
+            eq(get("userId"), int(1)),
+        );
+        assert_optimizes(
+            or([bool(false), get("beta"), bool(false)]),
+            or([get("beta")]),
+        );
+        assert_optimizes(
+            and([bool(true), or([bool(false), gt(get("age"), int(18))])]),
+            gt(get("age"), int(18)),
+        );
+        assert_optimizes(not(not(get("beta"))), not(not(get("beta"))));
+        assert_optimizes(
+            and([get("beta"), bool(false)]),
+            and([get("beta"), bool(false)]),
+        );
+    }
+
+    fn assert_same_results(
+        optimized: &dyn Expression,
+        original: &dyn Expression,
+        context: &Context,
+    ) {
+        assert_eq!(
+            format!("{:?}", optimized.eval_type(context)),
+            format!("{:?}", original.eval_type(context)),
+            "{} type checks differently",
+            original.to_json()
+        );
+        assert_eq!(
+            format!("{:?}", optimized.eval(context)),
+            format!("{:?}", original.eval(context)),
+            "{} evaluates differently",
+            original.to_json()
+        );
+    }
+
+    #[test]
+    fn it_keeps_errors() {
+        let context = Context::new().set_int("userId", 1).set_str("country", "DE");
+        let expressions = [
+            eq(int(1), str("a")),
+            and([int(1), bool(true)]),
+            not(not(get("userId"))),
+            or([bool(false), get("userId")]),
+            and([bool(true), get("missing")]),
+            gt(get("country"), int(1)),
+            and([bool(true), eq(get("country"), not(bool(false)))]),
+            or([bool(false), gt(get("userId"), str_array(["a", "b"]))]),
+        ];
+
+        for expression in expressions {
+            let optimized = expression.optimize();
+
+            assert_same_results(optimized.as_ref(), expression.as_ref(), &context);
+        }
+    }
+
+    #[test]
+    fn it_counts_the_same_steps() {
+        let expressions = [
+            and([eq(int(1), int(1)), gt(get("age"), int(18))]),
+            or([bool(false), not(not(eq(get("age"), int(21))))]),
+            and([eq(int_array([1, 2, 3]), int_array([1, 2, 3])), bool(true)]),
+        ];
+
+        for expression in expressions {
+            let optimized = expression.optimize();
+
+            for max_steps in 0..10 {
+                for max_value_size in 1..4 {
+                    let budget = || {
+                        EvalBudget::new()
+                            .set_max_steps(max_steps)
+                            .set_max_value_size(max_value_size)
+                    };
+                    let context = || Context::new().set_int("age", 21).set_budget(budget());
+
+                    assert_eq!(
+                        format!("{:?}", optimized.eval(&context())),
+                        format!("{:?}", expression.eval(&context()))
+                    );
+                    assert_eq!(
+                        format!("{:?}", optimized.eval_with_budget(&context(), &budget())),
+                        format!("{:?}", expression.eval_with_budget(&context(), &budget()))
+                    );
+                }
+            }
+
+            let steps = |expression: &dyn Expression| {
+                let budget = EvalBudget::new();
+                let context = Context::new().set_int("age", 21).set_budget(budget.clone());
+
+                expression.eval(&context).unwrap();
+                budget.steps()
+            };
+            assert_eq!(steps(optimized.as_ref()), steps(expression.as_ref()));
+        }
+    }
+}
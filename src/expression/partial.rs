@@ -0,0 +1,159 @@
+use crate::context::Context;
+use crate::expression::ops::get_name;
+use crate::expression::optimize::{remove_double_negation, unwrap_single_arg};
+use crate::expression::value::Value;
+use crate::expression::visit::Folder;
+use crate::expression::Expression;
+
+impl dyn Expression + '_ {
+    /// Residual expression for a context which is only partly known.
+    ///
+    /// Every `get` of a name provided by `partial_context` is replaced by its value and
+    /// the result is simplified, so evaluating the residual against the remaining values
+    /// gives the same result or error as evaluating `self` against all of them. `and` and
+    /// `or` drop the args they would never evaluate, so unlike with `optimize()` the
+    /// residual may type check where `self` would not. Ops which cannot be rebuilt with
+    /// `with_args()` keep their original args.
+    pub fn partially_evaluate(&self, partial_context: &Context) -> Box<dyn Expression> {
+        self.fold_with(&mut PartialEvaluator { partial_context })
+    }
+
+    /// Value of a bool literal, e.g. of a residual which no longer depends on the context.
+    pub fn as_constant_bool(&self) -> Option<bool> {
+        self.as_value()?.as_bool()
+    }
+}
+
+struct PartialEvaluator<'a> {
+    partial_context: &'a Context,
+}
+
+impl Folder for PartialEvaluator<'_> {
+    /// Substitutes `get`s of known names.
+    fn replace(&mut self, expression: &dyn Expression) -> Option<Box<dyn Expression>> {
+        let name = get_name(expression)?;
+
+        match self.partial_context.fetch(&name) {
+            Ok(value) => Some(Box::new(value.into_owned())),
+            Err(_) => None,
+        }
+    }
+
+    fn fold(&mut self, expression: Box<dyn Expression>) -> Box<dyn Expression> {
+        if expression.as_value().is_none() && expression.context_dependencies().is_none() {
+            if let Ok(value) = expression.eval(&Context::new()) {
+                return Box::new(value);
+            }
+        }
+
+        match expression.name() {
+            "and" => short_circuit(expression, false),
+            "or" => short_circuit(expression, true),
+            "not" => remove_double_negation(expression),
+            _ => expression,
+        }
+    }
+}
+
+/// Simplifies `and` (`on` is `false`) or `or` (`on` is `true`): literal `!on` args are
+/// dropped and args after a literal `on` are never evaluated.
+fn short_circuit(expression: Box<dyn Expression>, on: bool) -> Box<dyn Expression> {
+    let mut args: Vec<Box<dyn Expression>> = Vec::new();
+
+    for arg in expression.args() {
+        match arg.as_constant_bool() {
+            Some(content) if content != on => continue,
+            Some(_) => {
+                args.push(arg.clone());
+                break;
+            }
+            None => args.push(arg.clone()),
+        }
+    }
+
+    match args.first().and_then(|arg| arg.as_constant_bool()) {
+        Some(content) if content == on => return Box::new(Value::Bool(on)),
+        _ => {}
+    }
+
+    if args.is_empty() {
+        return Box::new(Value::Bool(!on));
+    }
+
+    match expression.with_args(args) {
+        Some(rebuilt) => unwrap_single_arg(rebuilt),
+        None => expression,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::expression::ops::*;
+    use crate::expression::{EvalError, Expression};
+    use crate::parser::parse;
+
+    #[test]
+    fn it_leaves_a_residual_of_missing_keys() {
+        let rule = parse(
+            r#"{"or": [
+                {"and": [{"eq": [{"get": ["plan"]}, "pro"]}, {"gt": [{"get": ["userId"]}, 100]}]},
+                {"eq": [{"get": ["region"]}, "eu"]}
+            ]}"#,
+        )
+        .unwrap();
+        let deploy_context = Context::new()
+            .set_str("plan", "pro")
+            .set_str("region", "us");
+
+        let residual = rule.partially_evaluate(&deploy_context);
+
+        assert_eq!(&residual, &gt(get("userId"), int(100)));
+        assert_eq!(
+            residual.context_dependencies(),
+            Some(vec!["userId".to_string()])
+        );
+        assert_eq!(residual.as_constant_bool(), None);
+    }
+
+    #[test]
+    fn it_reports_constant_residuals() {
+        let rule = and([eq(get("plan"), str("pro")), gt(get("userId"), int(100))]);
+
+        let residual = rule.partially_evaluate(&Context::new().set_str("plan", "free"));
+        assert_eq!(residual.as_constant_bool(), Some(false));
+
+        let residual = or([not(eq(get("plan"), str("free"))), get("beta")])
+            .partially_evaluate(&Context::new().set_str("plan", "pro"));
+        assert_eq!(residual.as_constant_bool(), Some(true));
+    }
+
+    #[test]
+    fn it_evaluates_like_the_full_context() {
+        fn outcome(expression: &dyn Expression, context: &Context) -> Result<String, String> {
+            expression
+                .eval(context)
+                .map(|value| format!("{:?}", value))
+                .map_err(|err: EvalError| format!("{:?}", err.kind()))
+        }
+
+        let rules = [
+            and([get("flag"), eq(get("userId"), int(1))]),
+            or([eq(get("plan"), int(1)), get("userId")]),
+            and([not(not(get("flag"))), gt(get("userId"), get("missing"))]),
+            eq(get("plan"), get("userId")),
+        ];
+        let partial = Context::new().set_bool("flag", true).set_str("plan", "pro");
+        let rest = Context::new().set_int("userId", 1);
+        let full = partial.clone().set_int("userId", 1);
+
+        for rule in rules {
+            let residual = rule.partially_evaluate(&partial);
+
+            assert_eq!(
+                outcome(residual.as_ref(), &rest),
+                outcome(rule.as_ref(), &full)
+            );
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! Structural `Clone`, `PartialEq`, `Eq` and `Hash` for expression trees.
+//!
+//! Two expressions are equal when they have the same op names, the same literal values
+//! and equal arguments. Floats are compared by their bits, except that all NaNs are
+//! equal to each other and `-0.0` is equal to `0.0`.
+
+use crate::expression::value::Value;
+use crate::expression::Expression;
+use std::any::Any;
+use std::hash::{Hash, Hasher};
+
+pub trait ExpressionClone {
+    fn clone_box(&self) -> Box<dyn Expression>;
+}
+
+impl<T> ExpressionClone for T
+where
+    T: Expression + Clone + 'static,
+{
+    fn clone_box(&self) -> Box<dyn Expression> {
+        Box::new(self.clone())
+    }
+}
+
+/// Concrete type of an expression, which lets the crate recognize its own ops whatever
+/// name a custom op uses.
+pub trait ExpressionAny {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T> ExpressionAny for T
+where
+    T: Expression + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Clone for Box<dyn Expression> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+// Comparing two `Box<dyn Expression>` values with `==` moves the right operand because of
+// rust-lang/rust#31740, so compare references instead: `&left == &right`.
+impl PartialEq for dyn Expression + '_ {
+    fn eq(&self, other: &Self) -> bool {
+        expression_eq(self, other)
+    }
+}
+
+impl Eq for dyn Expression {}
+
+impl Hash for dyn Expression {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_expression(self, state)
+    }
+}
+
+impl dyn Expression + '_ {
+    /// Content hash which does not change between builds, platforms or crate versions
+    /// unless the expression itself changes. Suitable for storing next to persisted rules.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = FingerprintHasher::new();
+        hash_expression(self, &mut hasher);
+        hasher.finish()
+    }
+}
+
+fn expression_eq(left: &dyn Expression, right: &dyn Expression) -> bool {
+    if left.name() != right.name() {
+        return false;
+    }
+
+    let values_eq = match (left.as_value(), right.as_value()) {
+        (Some(left), Some(right)) => value_eq(left, right),
+        (None, None) => true,
+        _ => false,
+    };
+
+    let left_args = left.args();
+    let right_args = right.args();
+
+    values_eq
+        && left_args.len() == right_args.len()
+        && left_args
+            .into_iter()
+            .zip(right_args)
+            .all(|(left, right)| left == right)
+}
+
+fn hash_expression<H: Hasher>(expression: &dyn Expression, state: &mut H) {
+    hash_str(expression.name(), state);
+
+    if let Some(value) = expression.as_value() {
+        hash_value(value, state);
+    }
+
+    let args = expression.args();
+    state.write_usize(args.len());
+
+    for arg in args {
+        hash_expression(arg.as_ref(), state);
+    }
+}
+
+fn canonical_float_bits(value: f64) -> u64 {
+    if value.is_nan() {
+        f64::NAN.to_bits()
+    } else if value == 0.0 {
+        0.0f64.to_bits()
+    } else {
+        value.to_bits()
+    }
+}
+
+fn value_eq(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Float(left), Value::Float(right)) => {
+            canonical_float_bits(*left) == canonical_float_bits(*right)
+        }
+        (Value::FloatArray(left), Value::FloatArray(right)) => {
+            left.len() == right.len()
+                && left.iter().zip(right).all(|(left, right)| {
+                    canonical_float_bits(*left) == canonical_float_bits(*right)
+                })
+        }
+        _ => left == right,
+    }
+}
+
+fn hash_str<H: Hasher>(content: &str, state: &mut H) {
+    state.write(content.as_bytes());
+    state.write_u8(0xff);
+}
+
+fn hash_value<H: Hasher>(value: &Value, state: &mut H) {
+    match value {
+        Value::Bool(content) => state.write_u8(*content as u8),
+        Value::BoolArray(content) => {
+            state.write_usize(content.len());
+            content.iter().for_each(|item| state.write_u8(*item as u8));
+        }
+        Value::Int(content) => state.write_i64(*content),
+        Value::IntArray(content) => {
+            state.write_usize(content.len());
+            content.iter().for_each(|item| state.write_i64(*item));
+        }
+        Value::Float(content) => state.write_u64(canonical_float_bits(*content)),
+        Value::FloatArray(content) => {
+            state.write_usize(content.len());
+            content
+                .iter()
+                .for_each(|item| state.write_u64(canonical_float_bits(*item)));
+        }
+        Value::Str(content) => hash_str(content, state),
+        Value::StrArray(content) => {
+            state.write_usize(content.len());
+            content.iter().for_each(|item| hash_str(item, state));
+        }
+    }
+}
+
+/// 64-bit FNV-1a which writes integers as little-endian bytes of fixed width,
+/// so that the result is the same on every platform.
+struct FingerprintHasher {
+    state: u64,
+}
+
+impl FingerprintHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    fn new() -> Self {
+        Self {
+            state: Self::OFFSET_BASIS,
+        }
+    }
+}
+
+impl Hasher for FingerprintHasher {
+    fn finish(&self) -> u64 {
+        self.state
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state ^= *byte as u64;
+            self.state = self.state.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expression::ops::*;
+    use crate::expression::Expression;
+    use crate::parser::parse;
+    use std::collections::HashSet;
+
+    #[test]
+    fn it_clones_expressions() {
+        let expression = eq(int(1), get("userId"));
+
+        assert_eq!(&expression.clone(), &expression);
+    }
+
+    #[test]
+    fn it_compares_structurally() {
+        let parsed = parse(r#"{"eq": [1, {"get": ["userId"]}]}"#).unwrap();
+
+        assert_eq!(&parsed, &eq(int(1), get("userId")));
+        assert_ne!(&parsed, &eq(int(2), get("userId")));
+        assert_ne!(&parsed, &eq(int(1), get("accountId")));
+        assert_ne!(&parsed, &gt(int(1), get("userId")));
+        assert_ne!(&int(1), &float(1.0));
+        assert_ne!(&str("a"), &str_array(["a"]));
+    }
+
+    #[test]
+    fn it_compares_floats_by_canonical_bits() {
+        assert_eq!(&float(f64::NAN), &float(-f64::NAN));
+        assert_eq!(&float(0.0), &float(-0.0));
+        assert_eq!(&float_array([f64::NAN, 1.5]), &float_array([f64::NAN, 1.5]));
+        assert_ne!(&float(0.1 + 0.2), &float(0.3));
+    }
+
+    #[test]
+    fn it_hashes_structurally() {
+        let mut expressions: HashSet<Box<dyn Expression>> = HashSet::new();
+
+        expressions.insert(eq(int(1), get("userId")));
+        expressions.insert(parse(r#"{"eq": [1, {"get": ["userId"]}]}"#).unwrap());
+        expressions.insert(eq(float(0.0), float(f64::NAN)));
+        expressions.insert(eq(float(-0.0), float(-f64::NAN)));
+
+        assert_eq!(expressions.len(), 2);
+    }
+
+    #[test]
+    fn it_has_stable_fingerprints() {
+        let expression = eq(int(1), get("userId"));
+
+        assert_eq!(expression.fingerprint(), expression.clone().fingerprint());
+        assert_ne!(
+            expression.fingerprint(),
+            eq(int(2), get("userId")).fingerprint()
+        );
+        assert_eq!(expression.fingerprint(), 5861471373030107088);
+    }
+}
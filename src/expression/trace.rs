@@ -0,0 +1,283 @@
+use crate::context::Context;
+use crate::expression::value::Value;
+use crate::expression::{EvalError, EvalResult, Expression};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+/// Record of evaluating an expression with `Expression::eval_traced()`.
+#[derive(Clone, Debug)]
+pub struct Trace {
+    name: String,
+    json: JsonValue,
+    outcome: TraceOutcome,
+    args: Vec<Trace>,
+}
+
+#[derive(Clone, Debug)]
+pub enum TraceOutcome {
+    Value(Value),
+    Error(EvalError),
+    /// Not evaluated, because `and` or `or` stopped early or an earlier arg failed.
+    Skipped,
+}
+
+impl Trace {
+    pub(crate) fn new(
+        name: &str,
+        json: JsonValue,
+        result: EvalResult<Value>,
+        args: Vec<Trace>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            json,
+            outcome: match result {
+                Ok(value) => TraceOutcome::Value(value),
+                Err(err) => TraceOutcome::Error(err),
+            },
+            args,
+        }
+    }
+
+    fn skipped(expression: &dyn Expression) -> Self {
+        Self {
+            name: expression.name().to_string(),
+            json: expression.to_json(),
+            outcome: TraceOutcome::Skipped,
+            args: Vec::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// `to_json()` of the traced expression.
+    pub fn json(&self) -> &JsonValue {
+        &self.json
+    }
+
+    pub fn outcome(&self) -> &TraceOutcome {
+        &self.outcome
+    }
+
+    pub fn value(&self) -> Option<&Value> {
+        match &self.outcome {
+            TraceOutcome::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn error(&self) -> Option<&EvalError> {
+        match &self.outcome {
+            TraceOutcome::Error(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    pub fn is_skipped(&self) -> bool {
+        matches!(self.outcome, TraceOutcome::Skipped)
+    }
+
+    /// Traces of the args, empty for literals, `get` and ops which do not trace their args.
+    pub fn args(&self) -> &[Trace] {
+        &self.args
+    }
+
+    /// Smallest set of leaf conditions which decided the result. For an `and` which is
+    /// `false` that is the arg which stopped it, for one which is `true` every arg, and
+    /// the other way round for `or`. For an error it is the node the error came from.
+    pub fn deciding_conditions(&self) -> Vec<&Trace> {
+        let mut conditions = Vec::new();
+        self.collect_deciding_conditions(&mut conditions);
+        conditions
+    }
+
+    fn collect_deciding_conditions<'a>(&'a self, conditions: &mut Vec<&'a Trace>) {
+        let mut evaluated = self.args.iter().filter(|arg| !arg.is_skipped());
+
+        match (&self.outcome, self.name.as_str()) {
+            (TraceOutcome::Skipped, _) => {}
+            (TraceOutcome::Error(_), _) => match self.args.iter().find(|arg| arg.error().is_some())
+            {
+                Some(arg) => arg.collect_deciding_conditions(conditions),
+                None => conditions.push(self),
+            },
+            (TraceOutcome::Value(Value::Bool(content)), "and" | "or") if !self.args.is_empty() => {
+                let stopped_on = self.name == "or";
+
+                if *content == stopped_on {
+                    if let Some(arg) = evaluated.next_back() {
+                        arg.collect_deciding_conditions(conditions);
+                    }
+                } else {
+                    for arg in evaluated {
+                        arg.collect_deciding_conditions(conditions);
+                    }
+                }
+            }
+            (TraceOutcome::Value(_), "not") if self.args.len() == 1 => {
+                self.args[0].collect_deciding_conditions(conditions)
+            }
+            (TraceOutcome::Value(_), _) => conditions.push(self),
+        }
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let mut json = JsonMap::new();
+        json.insert("expression".to_string(), self.json.clone());
+
+        match &self.outcome {
+            TraceOutcome::Value(value) => json.insert("value".to_string(), value.to_json()),
+            TraceOutcome::Error(err) => json.insert("error".to_string(), json!(err.to_string())),
+            TraceOutcome::Skipped => json.insert("skipped".to_string(), json!(true)),
+        };
+
+        if !self.args.is_empty() {
+            json.insert(
+                "args".to_string(),
+                self.args.iter().map(Trace::to_json).collect(),
+            );
+        }
+
+        JsonValue::Object(json)
+    }
+}
+
+/// Traces the args of `expression` in order after counting a budget step. After each
+/// arg `short_circuit` may finish the op early, and otherwise `combine` computes its
+/// result from all arg values. Args which are not reached are recorded as skipped.
+pub(crate) fn trace_args<S, C>(
+    expression: &dyn Expression,
+    context: &Context,
+    short_circuit: S,
+    combine: C,
+) -> Trace
+where
+    S: Fn(&Value) -> EvalResult<Option<Value>>,
+    C: FnOnce(&[&Value]) -> EvalResult<Value>,
+{
+    let args = expression.args();
+    let mut traces = Vec::with_capacity(args.len());
+    let mut result = context.check_budget().err().map(Err);
+
+    for arg in args {
+        if result.is_some() {
+            traces.push(Trace::skipped(arg.as_ref()));
+            continue;
+        }
+
+        let trace = arg.eval_traced(context);
+
+        result = match &trace.outcome {
+            TraceOutcome::Value(value) => short_circuit(value).transpose(),
+            TraceOutcome::Error(err) => Some(Err(err.clone())),
+            TraceOutcome::Skipped => None,
+        };
+
+        traces.push(trace);
+    }
+
+    let result = result.unwrap_or_else(|| {
+        let values: Vec<&Value> = traces.iter().filter_map(Trace::value).collect();
+        combine(&values)
+    });
+
+    Trace::new(expression.name(), expression.to_json(), result, traces)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::Context;
+    use crate::expression::ops::*;
+    use crate::expression::value::Value;
+    use crate::parser::parse;
+    use serde_json::json;
+
+    #[test]
+    fn it_traces_evaluated_nodes() {
+        let context = Context::new().set_int("age", 16).set_str("country", "DE");
+        let expression = and([
+            eq(get("country"), str("DE")),
+            gt(get("age"), int(18)),
+            get("beta"),
+        ]);
+
+        let trace = expression.eval_traced(&context);
+
+        assert_eq!(trace.value(), Some(&Value::Bool(false)));
+        assert_eq!(
+            trace.to_json(),
+            json!({
+                "expression": expression.to_json(),
+                "value": false,
+                "args": [
+                    {
+                        "expression": {"eq": [{"get": ["country"]}, "DE"]},
+                        "value": true,
+                        "args": [
+                            {"expression": {"get": ["country"]}, "value": "DE"},
+                            {"expression": "DE", "value": "DE"}
+                        ]
+                    },
+                    {
+                        "expression": {"gt": [{"get": ["age"]}, 18]},
+                        "value": false,
+                        "args": [
+                            {"expression": {"get": ["age"]}, "value": 16},
+                            {"expression": 18, "value": 18}
+                        ]
+                    },
+                    {"expression": {"get": ["beta"]}, "skipped": true}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn it_traces_errors() {
+        let expression = or([get("missing"), bool(true)]);
+
+        let trace = expression.eval_traced(&Context::new());
+
+        assert_eq!(
+            trace.error().unwrap().to_string(),
+            expression.eval(&Context::new()).unwrap_err().to_string()
+        );
+        assert!(trace.args()[1].is_skipped());
+        assert_eq!(
+            trace.deciding_conditions()[0].json(),
+            &json!({"get": ["missing"]})
+        );
+    }
+
+    #[test]
+    fn it_finds_deciding_conditions() {
+        let expression = parse(
+            r#"{"or": [
+                {"and": [{"eq": [{"get": ["plan"]}, "pro"]}, {"gt": [{"get": ["age"]}, 18]}]},
+                {"not": [{"eq": [{"get": ["country"]}, "DE"]}]}
+            ]}"#,
+        )
+        .unwrap();
+        let context = Context::new()
+            .set_str("plan", "pro")
+            .set_int("age", 16)
+            .set_str("country", "DE");
+
+        let trace = expression.eval_traced(&context);
+        let conditions: Vec<_> = trace
+            .deciding_conditions()
+            .into_iter()
+            .map(|condition| condition.json().clone())
+            .collect();
+
+        assert_eq!(trace.value(), Some(&Value::Bool(false)));
+        assert_eq!(
+            conditions,
+            [
+                json!({"gt": [{"get": ["age"]}, 18]}),
+                json!({"eq": [{"get": ["country"]}, "DE"]})
+            ]
+        );
+    }
+}
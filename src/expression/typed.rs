@@ -0,0 +1,365 @@
+//! Typed layer over `ops`. `Expr<T>` only allows combinations which type check, so
+//! `get_int("age").gt(18) & get_str("country").eq("DE")` compiles while comparing an
+//! int with a string does not. Array lengths are not part of `T` and are still checked
+//! by `eval_type()`.
+
+use crate::context::Context;
+use crate::expression::eval_type::Type;
+use crate::expression::ops;
+use crate::expression::{EvalError, EvalErrorKind, EvalResult, Expression};
+use std::marker::PhantomData;
+use std::ops::{BitAnd, BitOr, Not};
+
+/// Expression known to evaluate to a value of Rust type `T`.
+pub struct Expr<T> {
+    expression: Box<dyn Expression>,
+    value_type: PhantomData<fn() -> T>,
+}
+
+mod sealed {
+    use crate::expression::eval_type::Type;
+
+    pub trait Sealed {
+        /// Type of a value of this Rust type, with `size` items if it is an array.
+        fn value_type(size: usize) -> Type;
+    }
+}
+
+/// Rust types with a matching `Value` variant.
+pub trait ExprType: sealed::Sealed {}
+
+macro_rules! impl_expr_type {
+    ($($value_type:ty => $type_of_size:expr),+ $(,)?) => {
+        $(
+            impl sealed::Sealed for $value_type {
+                fn value_type(size: usize) -> Type {
+                    $type_of_size(size)
+                }
+            }
+            impl ExprType for $value_type {}
+        )+
+    };
+}
+
+impl_expr_type!(
+    bool => |_| Type::Bool,
+    Vec<bool> => Type::BoolArray,
+    i64 => |_| Type::Int,
+    Vec<i64> => Type::IntArray,
+    f64 => |_| Type::Float,
+    Vec<f64> => Type::FloatArray,
+    String => |_| Type::Str,
+    Vec<String> => Type::StrArray,
+);
+
+/// Conversion of literals and typed expressions into `Expr<T>`.
+pub trait IntoExpr<T> {
+    fn into_expr(self) -> Expr<T>;
+}
+
+impl<T> IntoExpr<T> for Expr<T> {
+    fn into_expr(self) -> Expr<T> {
+        self
+    }
+}
+
+macro_rules! impl_into_expr {
+    ($value_type:ty, $($literal_type:ty => $convert:expr),+ $(,)?) => {
+        $(
+            impl IntoExpr<$value_type> for $literal_type {
+                fn into_expr(self) -> Expr<$value_type> {
+                    let convert: fn($literal_type) -> Box<dyn Expression> = $convert;
+                    Expr::new(convert(self))
+                }
+            }
+        )+
+    };
+}
+
+impl_into_expr!(bool, bool => ops::bool);
+impl_into_expr!(Vec<bool>, Vec<bool> => ops::bool_array);
+impl_into_expr!(i64, i64 => ops::int);
+impl_into_expr!(Vec<i64>, Vec<i64> => ops::int_array);
+impl_into_expr!(f64, f64 => ops::float);
+impl_into_expr!(Vec<f64>, Vec<f64> => ops::float_array);
+impl_into_expr!(String, String => ops::str, &str => |content| ops::str(content));
+impl_into_expr!(
+    Vec<String>,
+    Vec<String> => ops::str_array,
+    Vec<&str> => |content| ops::str_array(content),
+);
+
+impl<const N: usize> IntoExpr<Vec<bool>> for [bool; N] {
+    fn into_expr(self) -> Expr<Vec<bool>> {
+        Expr::new(ops::bool_array(self))
+    }
+}
+
+impl<const N: usize> IntoExpr<Vec<i64>> for [i64; N] {
+    fn into_expr(self) -> Expr<Vec<i64>> {
+        Expr::new(ops::int_array(self))
+    }
+}
+
+impl<const N: usize> IntoExpr<Vec<f64>> for [f64; N] {
+    fn into_expr(self) -> Expr<Vec<f64>> {
+        Expr::new(ops::float_array(self))
+    }
+}
+
+impl<const N: usize> IntoExpr<Vec<String>> for [&str; N] {
+    fn into_expr(self) -> Expr<Vec<String>> {
+        Expr::new(ops::str_array(self))
+    }
+}
+
+impl<T> Expr<T> {
+    fn new(expression: Box<dyn Expression>) -> Self {
+        Self {
+            expression,
+            value_type: PhantomData,
+        }
+    }
+
+    pub fn as_expression(&self) -> &dyn Expression {
+        self.expression.as_ref()
+    }
+
+    pub fn into_expression(self) -> Box<dyn Expression> {
+        self.expression
+    }
+}
+
+impl<T> Expr<T>
+where
+    T: ExprType,
+{
+    /// Wraps an untyped expression whose `eval_type()` against `context` is `T`.
+    pub fn from_expression(expression: Box<dyn Expression>, context: &Context) -> EvalResult<Self> {
+        let actual = expression.eval_type(context)?;
+        let expected = T::value_type(match actual {
+            Type::BoolArray(size)
+            | Type::IntArray(size)
+            | Type::FloatArray(size)
+            | Type::StrArray(size) => size,
+            _ => 0,
+        });
+
+        if actual != expected {
+            return Err(EvalError {
+                error_kind: EvalErrorKind::ValueTypeMismatch { expected, actual },
+            });
+        }
+
+        Ok(Self::new(expression))
+    }
+
+    pub fn eq<R>(self, other: R) -> Expr<bool>
+    where
+        R: IntoExpr<T>,
+    {
+        Expr::new(ops::eq(self.expression, other.into_expr().expression))
+    }
+
+    pub fn ne<R>(self, other: R) -> Expr<bool>
+    where
+        R: IntoExpr<T>,
+    {
+        !self.eq(other)
+    }
+
+    pub fn gt<R>(self, other: R) -> Expr<bool>
+    where
+        R: IntoExpr<T>,
+    {
+        Expr::new(ops::gt(self.expression, other.into_expr().expression))
+    }
+
+    pub fn lt<R>(self, other: R) -> Expr<bool>
+    where
+        R: IntoExpr<T>,
+    {
+        other.into_expr().gt(self)
+    }
+
+    /// Spelled as `gt` or `eq`, so that NaN compares the same way as with those ops.
+    pub fn ge<R>(self, other: R) -> Expr<bool>
+    where
+        R: IntoExpr<T>,
+    {
+        let other = other.into_expr();
+        self.clone().gt(other.clone()) | self.eq(other)
+    }
+
+    /// Spelled like `ge()`, with the sides of `gt` swapped.
+    pub fn le<R>(self, other: R) -> Expr<bool>
+    where
+        R: IntoExpr<T>,
+    {
+        let other = other.into_expr();
+        other.clone().gt(self.clone()) | self.eq(other)
+    }
+}
+
+impl<T> Clone for Expr<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.expression.clone())
+    }
+}
+
+impl<T> From<Expr<T>> for Box<dyn Expression> {
+    fn from(expr: Expr<T>) -> Self {
+        expr.expression
+    }
+}
+
+/// Arguments of `expression` if it is a `name` op, so that chains like `a & b & c`
+/// build a single `and` instead of nesting them.
+fn flatten(expression: Box<dyn Expression>, name: &str) -> Vec<Box<dyn Expression>> {
+    if expression.name() == name {
+        expression.args().into_iter().cloned().collect()
+    } else {
+        vec![expression]
+    }
+}
+
+impl<R> BitAnd<R> for Expr<bool>
+where
+    R: IntoExpr<bool>,
+{
+    type Output = Expr<bool>;
+
+    fn bitand(self, other: R) -> Expr<bool> {
+        let mut args = flatten(self.expression, "and");
+        args.extend(flatten(other.into_expr().expression, "and"));
+        Expr::new(ops::and(args))
+    }
+}
+
+impl<R> BitOr<R> for Expr<bool>
+where
+    R: IntoExpr<bool>,
+{
+    type Output = Expr<bool>;
+
+    fn bitor(self, other: R) -> Expr<bool> {
+        let mut args = flatten(self.expression, "or");
+        args.extend(flatten(other.into_expr().expression, "or"));
+        Expr::new(ops::or(args))
+    }
+}
+
+impl Not for Expr<bool> {
+    type Output = Expr<bool>;
+
+    fn not(self) -> Expr<bool> {
+        Expr::new(ops::not(self.expression))
+    }
+}
+
+/// Typed literal, e.g. `lit(18).lt(get_int("age"))`.
+pub fn lit<T, V>(value: V) -> Expr<T>
+where
+    V: IntoExpr<T>,
+{
+    value.into_expr()
+}
+
+macro_rules! typed_get {
+    ($($fn_name:ident => $value_type:ty),+ $(,)?) => {
+        $(
+            pub fn $fn_name<S>(name: S) -> Expr<$value_type>
+            where
+                S: Into<String>,
+            {
+                Expr::new(ops::get(name))
+            }
+        )+
+    };
+}
+
+typed_get!(
+    get_bool => bool,
+    get_bool_array => Vec<bool>,
+    get_int => i64,
+    get_int_array => Vec<i64>,
+    get_float => f64,
+    get_float_array => Vec<f64>,
+    get_str => String,
+    get_str_array => Vec<String>,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::ops::*;
+
+    #[test]
+    fn it_builds_typed_expressions() {
+        let expression: Box<dyn Expression> =
+            (get_int("age").gt(18) & get_str("country").eq("DE") & !get_bool("banned")).into();
+        let expected = and(vec![
+            gt(get("age"), int(18)),
+            eq(get("country"), str("DE")),
+            not(get("banned")),
+        ]);
+        let context = Context::new()
+            .set_int("age", 21)
+            .set_str("country", "DE")
+            .set_bool("banned", false);
+
+        assert_eq!(&expression, &expected);
+        assert_eq!(expression.eval_type(&context).unwrap(), Type::Bool);
+        assert!(expression.eval_bool(&context).unwrap());
+    }
+
+    #[test]
+    fn it_checks_the_type_of_untyped_expressions() {
+        let context = Context::new()
+            .set_int("age", 21)
+            .set_int_array("ids", [1, 2]);
+
+        assert!(Expr::<i64>::from_expression(get("age"), &context).is_ok());
+        assert!(Expr::<Vec<i64>>::from_expression(get("ids"), &context).is_ok());
+        assert!(matches!(
+            Expr::<String>::from_expression(get("age"), &context)
+                .err()
+                .unwrap()
+                .kind(),
+            EvalErrorKind::ValueTypeMismatch {
+                expected: Type::Str,
+                actual: Type::Int
+            }
+        ));
+        assert!(Expr::<bool>::from_expression(get("missing"), &context).is_err());
+    }
+
+    #[test]
+    fn it_spells_derived_comparisons_with_eq_and_gt() {
+        let context = Context::new()
+            .set_float("score", 0.5)
+            .set_str_array("roles", ["admin".to_string()]);
+
+        let expression = get_float("score").le(0.5) | get_str_array("roles").ne(["dev"]);
+
+        assert_eq!(
+            &expression.clone().into_expression(),
+            &or(vec![
+                gt(float(0.5), get("score")),
+                eq(get("score"), float(0.5)),
+                not(eq(get("roles"), str_array(["dev"]))),
+            ])
+        );
+        assert!(expression.as_expression().eval_bool(&context).unwrap());
+        assert!(lit(1)
+            .ge(get_int("missing"))
+            .as_expression()
+            .eval(&context)
+            .is_err());
+        assert!(!lit(0.5)
+            .lt(get_float("score"))
+            .as_expression()
+            .eval_bool(&context)
+            .unwrap());
+    }
+}
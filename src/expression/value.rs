@@ -2,6 +2,7 @@ use crate::context::Context;
 use crate::expression::eval_type::Type;
 use crate::expression::{EvalResult, Expression};
 use serde_json::{json, Value as JsonValue};
+use std::borrow::Cow;
 
 pub fn bool_val(content: bool) -> Value {
     Value::Bool(content)
@@ -111,6 +112,66 @@ pub enum Value {
     StrArray(Vec<String>),
 }
 
+impl From<bool> for Value {
+    fn from(content: bool) -> Self {
+        Value::Bool(content)
+    }
+}
+
+impl From<Vec<bool>> for Value {
+    fn from(content: Vec<bool>) -> Self {
+        Value::BoolArray(content)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(content: i64) -> Self {
+        Value::Int(content)
+    }
+}
+
+impl From<Vec<i64>> for Value {
+    fn from(content: Vec<i64>) -> Self {
+        Value::IntArray(content)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(content: f64) -> Self {
+        Value::Float(content)
+    }
+}
+
+impl From<Vec<f64>> for Value {
+    fn from(content: Vec<f64>) -> Self {
+        Value::FloatArray(content)
+    }
+}
+
+impl From<String> for Value {
+    fn from(content: String) -> Self {
+        Value::Str(content)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(content: &str) -> Self {
+        Value::Str(content.to_string())
+    }
+}
+
+impl From<Vec<String>> for Value {
+    fn from(content: Vec<String>) -> Self {
+        Value::StrArray(content)
+    }
+}
+
+impl From<Vec<&str>> for Value {
+    fn from(content: Vec<&str>) -> Self {
+        str_array_val(content)
+    }
+}
+
 impl Value {
     pub fn concrete_type(&self) -> Type {
         match self {
@@ -125,6 +186,18 @@ impl Value {
         }
     }
 
+    /// Number of items for arrays, number of bytes for strings and 1 for other scalars.
+    pub fn size(&self) -> usize {
+        match self {
+            Value::BoolArray(content) => content.len(),
+            Value::IntArray(content) => content.len(),
+            Value::FloatArray(content) => content.len(),
+            Value::Str(content) => content.len(),
+            Value::StrArray(content) => content.len(),
+            _ => 1,
+        }
+    }
+
     pub fn as_bool(&self) -> Option<bool> {
         match self {
             Value::Bool(content) => Some(*content),
@@ -195,8 +268,14 @@ impl Value {
 }
 
 impl Expression for Value {
-    fn eval(&self, _context: &Context) -> EvalResult<Value> {
-        Ok(self.clone())
+    fn eval(&self, context: &Context) -> EvalResult<Value> {
+        self.eval_cow(context).map(Cow::into_owned)
+    }
+
+    fn eval_cow<'a>(&'a self, context: &'a Context) -> EvalResult<Cow<'a, Value>> {
+        context.check_budget()?;
+        context.check_value_size(self)?;
+        Ok(Cow::Borrowed(self))
     }
 
     fn eval_type(&self, _context: &Context) -> EvalResult<Type> {
@@ -220,10 +299,22 @@ impl Expression for Value {
         }
     }
 
+    fn as_value(&self) -> Option<&Value> {
+        Some(self)
+    }
+
     fn args(&self) -> Vec<&Box<dyn Expression>> {
         Vec::new()
     }
 
+    fn with_args(&self, args: Vec<Box<dyn Expression>>) -> Option<Box<dyn Expression>> {
+        if args.is_empty() {
+            Some(Box::new(self.clone()))
+        } else {
+            None
+        }
+    }
+
     fn to_json(&self) -> JsonValue {
         match self {
             Value::Bool(content) => json!(content),
@@ -237,3 +328,70 @@ impl Expression for Value {
         }
     }
 }
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.to_json().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let json = JsonValue::deserialize(deserializer)?;
+        crate::context::json_to_value(&json, "").map_err(D::Error::custom)
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn it_serializes_values() {
+        assert_eq!(serde_json::to_value(int_val(1)).unwrap(), json!(1));
+        assert_eq!(
+            serde_json::to_value(str_array_val(["a", "b"])).unwrap(),
+            json!(["a", "b"])
+        );
+    }
+
+    #[test]
+    fn it_deserializes_values() {
+        assert_eq!(
+            serde_json::from_value::<Value>(json!(1.5)).unwrap(),
+            float_val(1.5)
+        );
+        assert_eq!(
+            serde_json::from_value::<Value>(json!([true, false])).unwrap(),
+            bool_array_val([true, false])
+        );
+        assert!(serde_json::from_value::<Value>(json!({"get": ["userId"]})).is_err());
+        assert!(serde_json::from_value::<Value>(json!(null)).is_err());
+    }
+
+    #[test]
+    fn it_deserializes_values_beyond_parser_limits() {
+        let ids: Vec<i64> = (0..20_000).collect();
+        let name = "a".repeat(100_000);
+
+        assert_eq!(
+            serde_json::from_value::<Value>(json!(ids)).unwrap(),
+            Value::IntArray(ids)
+        );
+        assert_eq!(
+            serde_json::from_value::<Value>(json!(name)).unwrap(),
+            Value::Str(name)
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use crate::expression::Expression;
+
+/// Walks an expression tree with `walk()`, depth first and args in order.
+///
+/// `path` holds the position of each node among its parent's args, starting from the
+/// root, so the root has an empty path.
+pub trait Visitor {
+    /// Called before the args of `expression`. Returning `false` skips them.
+    fn enter(&mut self, _expression: &dyn Expression, _path: &[usize]) -> bool {
+        true
+    }
+
+    /// Called after the args of `expression`, unless `enter()` skipped them.
+    fn leave(&mut self, _expression: &dyn Expression, _path: &[usize]) {}
+}
+
+/// Rewrites an expression tree with `fold_with()`, building a new tree bottom up.
+pub trait Folder {
+    /// Called before the args of `expression` are folded. Returning a replacement skips
+    /// the args and `fold()` of `expression`.
+    fn replace(&mut self, _expression: &dyn Expression) -> Option<Box<dyn Expression>> {
+        None
+    }
+
+    /// Called with a node rebuilt from its folded args. Ops which cannot be rebuilt with
+    /// `with_args()` are passed as they were, with their original args.
+    fn fold(&mut self, expression: Box<dyn Expression>) -> Box<dyn Expression> {
+        expression
+    }
+}
+
+impl dyn Expression + '_ {
+    pub fn walk<V>(&self, visitor: &mut V)
+    where
+        V: Visitor + ?Sized,
+    {
+        self.walk_path(visitor, &mut Vec::new());
+    }
+
+    fn walk_path<V>(&self, visitor: &mut V, path: &mut Vec<usize>)
+    where
+        V: Visitor + ?Sized,
+    {
+        if !visitor.enter(self, path) {
+            return;
+        }
+
+        for (position, arg) in self.args().into_iter().enumerate() {
+            path.push(position);
+            arg.walk_path(visitor, path);
+            path.pop();
+        }
+
+        visitor.leave(self, path);
+    }
+
+    /// JSON Pointer into `to_json()` of the node at `path`, e.g. `/and/1/eq/0`, or `None`
+    /// if there is no such node.
+    pub fn json_pointer(&self, path: &[usize]) -> Option<String> {
+        let (position, rest) = match path.split_first() {
+            Some(first) => first,
+            None => return Some(String::new()),
+        };
+        let args = self.args();
+        let name = self.name().replace('~', "~0").replace('/', "~1");
+
+        Some(format!(
+            "/{}/{}{}",
+            name,
+            position,
+            args.get(*position)?.json_pointer(rest)?
+        ))
+    }
+
+    /// Tree rebuilt by `folder`. An op whose `with_args()` returns `None` is passed to
+    /// `fold()` with its original args, so the rewrites of its subtree are discarded.
+    pub fn fold_with<F>(&self, folder: &mut F) -> Box<dyn Expression>
+    where
+        F: Folder + ?Sized,
+    {
+        if let Some(replacement) = folder.replace(self) {
+            return replacement;
+        }
+
+        let args = self
+            .args()
+            .into_iter()
+            .map(|arg| arg.fold_with(folder))
+            .collect();
+
+        let expression = self.with_args(args).unwrap_or_else(|| self.clone_box());
+        folder.fold(expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+    use crate::expression::eval_type::Type;
+    use crate::expression::ops::*;
+    use crate::expression::value::Value;
+    use crate::expression::{args_context_dependencies, EvalResult};
+
+    #[test]
+    fn it_walks_nodes_with_paths() {
+        struct Names(Vec<(String, Vec<usize>)>);
+
+        impl Visitor for Names {
+            fn enter(&mut self, expression: &dyn Expression, path: &[usize]) -> bool {
+                self.0.push((expression.name().to_string(), path.to_vec()));
+                expression.name() != "get"
+            }
+        }
+
+        let expression = and([eq(get("userId"), int(1)), not(bool(false))]);
+        let mut names = Names(Vec::new());
+
+        expression.walk(&mut names);
+
+        assert_eq!(expression.json_pointer(&[0, 1]).unwrap(), "/and/0/eq/1");
+        assert_eq!(expression.json_pointer(&[2]), None);
+        assert_eq!(
+            names.0,
+            [
+                ("and".to_string(), vec![]),
+                ("eq".to_string(), vec![0]),
+                ("get".to_string(), vec![0, 0]),
+                ("int".to_string(), vec![0, 1]),
+                ("not".to_string(), vec![1]),
+                ("bool".to_string(), vec![1, 0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_rewrites_trees() {
+        struct IncrementInts;
+
+        impl Folder for IncrementInts {
+            fn fold(&mut self, expression: Box<dyn Expression>) -> Box<dyn Expression> {
+                match expression.as_value() {
+                    Some(Value::Int(content)) => int(content + 1),
+                    _ => expression,
+                }
+            }
+        }
+
+        struct RenameUserId;
+
+        impl Folder for RenameUserId {
+            fn replace(&mut self, expression: &dyn Expression) -> Option<Box<dyn Expression>> {
+                let name = expression.args().first()?.as_value()?.as_str()?;
+
+                if expression.name() == "get" && name == "userId" {
+                    Some(get("user.id"))
+                } else {
+                    None
+                }
+            }
+        }
+
+        let expression = or([gt(get("userId"), int(1)), eq(get("age"), int(2))]);
+        let opaque: Box<dyn Expression> = Box::new(Opaque {
+            args: vec![get("userId"), int(1)],
+        });
+
+        assert_eq!(
+            &or([opaque.clone(), int(1)]).fold_with(&mut IncrementInts),
+            &or([opaque.clone(), int(2)])
+        );
+        assert_eq!(&opaque.fold_with(&mut RenameUserId), &opaque);
+        assert_eq!(
+            &expression.fold_with(&mut IncrementInts),
+            &or([gt(get("userId"), int(2)), eq(get("age"), int(3))])
+        );
+        assert_eq!(
+            &expression.fold_with(&mut RenameUserId),
+            &or([gt(get("user.id"), int(1)), eq(get("age"), int(2))])
+        );
+    }
+
+    /// Op which does not implement `with_args()`.
+    #[derive(Clone)]
+    struct Opaque {
+        args: Vec<Box<dyn Expression>>,
+    }
+
+    impl Expression for Opaque {
+        fn eval(&self, _context: &Context) -> EvalResult<Value> {
+            Ok(Value::Bool(true))
+        }
+
+        fn eval_type(&self, _context: &Context) -> EvalResult<Type> {
+            Ok(Type::Bool)
+        }
+
+        fn context_dependencies(&self) -> Option<Vec<String>> {
+            args_context_dependencies(self)
+        }
+
+        fn name(&self) -> &str {
+            "opaque"
+        }
+
+        fn args(&self) -> Vec<&Box<dyn Expression>> {
+            self.args.iter().collect()
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use crate::analysis::satisfiability;
+use crate::context::Context;
+use crate::expression::ops::{get_name, not};
+use crate::expression::value::Value;
+use crate::expression::visit::Visitor;
+use crate::expression::Expression;
+use crate::schema::{Schema, SchemaType};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+/// Contexts generated by `generate_examples()`.
+#[derive(Clone, Debug, Default)]
+pub struct Examples {
+    satisfying: Vec<Context>,
+    falsifying: Vec<Context>,
+}
+
+impl Examples {
+    /// Contexts which make the expression evaluate to `true`.
+    pub fn satisfying(&self) -> &[Context] {
+        &self.satisfying
+    }
+
+    /// Contexts which make the expression evaluate to `false`.
+    pub fn falsifying(&self) -> &[Context] {
+        &self.falsifying
+    }
+}
+
+/// Contexts which make `expression` evaluate to `true` and to `false`, e.g. to build a
+/// test suite for a rule.
+///
+/// Every field of `schema` gets a value of its type, except nullable fields which
+/// `expression` does not read. Keys missing from `schema` take the type of the first
+/// literal they are compared with. Literals of another type than their key are not
+/// used as values, and neither are such values in witnesses, since a context holding
+/// them would not match the schema. Starting from the witnesses of `satisfiability()` for
+/// `expression` and its negation, and from a context of default values, one key at a
+/// time is set to each literal it is compared with by `eq` or `gt` and to the values
+/// right around it, e.g. `16`, `17` and `18` for `age > 17`. Contexts which make
+/// evaluation fail are dropped.
+pub fn generate_examples(expression: &dyn Expression, schema: &Schema) -> Examples {
+    let mut collector = LiteralCollector::default();
+    expression.walk(&mut collector);
+
+    let types = field_types(schema, &collector);
+    let mut candidates: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    let mut defaults = Context::new();
+
+    for (name, &schema_type) in &types {
+        let mut values = default_values(schema_type);
+
+        for literal in collector.literals.get(name).into_iter().flatten() {
+            if SchemaType::of(&literal.concrete_type()) == schema_type {
+                for value in boundary_values(literal) {
+                    if !values.contains(&value) {
+                        values.push(value);
+                    }
+                }
+            }
+        }
+
+        defaults.insert(name.as_str(), values[0].clone());
+        candidates.insert(name.clone(), values);
+    }
+
+    let mut bases = Vec::new();
+
+    for witness in [
+        satisfiability(expression),
+        satisfiability(not(expression.clone_box()).as_ref()),
+    ] {
+        if let Some(witness) = witness.witness() {
+            let mut base = defaults.clone();
+
+            for (name, value) in witness.iter() {
+                if types.get(name) == Some(&SchemaType::of(&value.concrete_type())) {
+                    base.insert(name, value.clone());
+                }
+            }
+
+            bases.push(base);
+        }
+    }
+
+    bases.push(defaults);
+
+    let mut examples = Examples::default();
+    let mut seen = HashSet::new();
+
+    for base in bases {
+        let mut contexts = vec![base.clone()];
+
+        for (name, values) in &candidates {
+            for value in values {
+                contexts.push(base.clone().set(name.as_str(), value.clone()));
+            }
+        }
+
+        for context in contexts {
+            if !seen.insert(context.to_json().to_string()) {
+                continue;
+            }
+
+            match expression.eval(&context) {
+                Ok(Value::Bool(true)) => examples.satisfying.push(context),
+                Ok(Value::Bool(false)) => examples.falsifying.push(context),
+                _ => {}
+            }
+        }
+    }
+
+    examples
+}
+
+/// Literals compared with each key, and every key which is read.
+#[derive(Default)]
+struct LiteralCollector {
+    literals: BTreeMap<String, Vec<Value>>,
+    names: BTreeSet<String>,
+}
+
+impl Visitor for LiteralCollector {
+    fn enter(&mut self, expression: &dyn Expression, _path: &[usize]) -> bool {
+        if let Some(name) = get_name(expression) {
+            self.names.insert(name);
+            return false;
+        }
+
+        if let ("eq" | "gt", [left, right]) = (expression.name(), expression.args().as_slice()) {
+            let comparison = match (get_name(left.as_ref()), right.as_value()) {
+                (Some(name), Some(literal)) => Some((name, literal)),
+                _ => get_name(right.as_ref()).zip(left.as_value()),
+            };
+
+            if let Some((name, literal)) = comparison {
+                self.literals.entry(name).or_default().push(literal.clone());
+            }
+        }
+
+        true
+    }
+}
+
+/// Type of every field to fill in: the fields of `schema`, leaving out nullable ones
+/// which are not read, and keys which are read but not declared.
+fn field_types(schema: &Schema, collector: &LiteralCollector) -> BTreeMap<String, SchemaType> {
+    let mut types: BTreeMap<String, SchemaType> = schema
+        .iter()
+        .filter(|(name, field)| !field.nullable || collector.names.contains(*name))
+        .map(|(name, field)| (name.to_string(), field.schema_type))
+        .collect();
+
+    for name in &collector.names {
+        if !types.contains_key(name) {
+            let schema_type = match collector
+                .literals
+                .get(name)
+                .and_then(|literals| literals.first())
+            {
+                Some(literal) => SchemaType::of(&literal.concrete_type()),
+                None => SchemaType::Bool,
+            };
+
+            types.insert(name.clone(), schema_type);
+        }
+    }
+
+    types
+}
+
+fn default_values(schema_type: SchemaType) -> Vec<Value> {
+    match schema_type {
+        SchemaType::Bool => vec![Value::Bool(false), Value::Bool(true)],
+        SchemaType::BoolArray => vec![Value::BoolArray(Vec::new())],
+        SchemaType::Int => vec![Value::Int(0)],
+        SchemaType::IntArray => vec![Value::IntArray(Vec::new())],
+        SchemaType::Float => vec![Value::Float(0.0)],
+        SchemaType::FloatArray => vec![Value::FloatArray(Vec::new())],
+        SchemaType::Str => vec![Value::Str(String::new())],
+        SchemaType::StrArray => vec![Value::StrArray(Vec::new())],
+    }
+}
+
+/// `literal` with the values just below and above it. Arrays get one item less and
+/// one item more.
+fn boundary_values(literal: &Value) -> Vec<Value> {
+    fn around<T: Clone>(items: &[T], extra: T) -> [Vec<T>; 3] {
+        let mut more = items.to_vec();
+        more.push(extra);
+        [
+            items[..items.len().saturating_sub(1)].to_vec(),
+            items.to_vec(),
+            more,
+        ]
+    }
+
+    match literal {
+        Value::Bool(_) => vec![Value::Bool(false), Value::Bool(true)],
+        Value::Int(content) => [
+            content.checked_sub(1),
+            Some(*content),
+            content.checked_add(1),
+        ]
+        .into_iter()
+        .flatten()
+        .map(Value::Int)
+        .collect(),
+        Value::Float(content) if content.is_nan() => Vec::new(),
+        Value::Float(content) => [next_down(*content), *content, next_up(*content)]
+            .into_iter()
+            .map(Value::Float)
+            .collect(),
+        Value::Str(content) => {
+            let mut shorter = content.clone();
+            shorter.pop();
+            [shorter, content.clone(), format!("{}a", content)]
+                .into_iter()
+                .map(Value::Str)
+                .collect()
+        }
+        Value::BoolArray(items) => around(items, false).map(Value::BoolArray).into(),
+        Value::IntArray(items) => around(items, 0).map(Value::IntArray).into(),
+        Value::FloatArray(items) => around(items, 0.0).map(Value::FloatArray).into(),
+        Value::StrArray(items) => around(items, String::new()).map(Value::StrArray).into(),
+    }
+}
+
+/// Smallest float greater than `content`.
+fn next_up(content: f64) -> f64 {
+    if content.is_nan() || content == f64::INFINITY {
+        content
+    } else if content == 0.0 {
+        f64::from_bits(1)
+    } else if content > 0.0 {
+        f64::from_bits(content.to_bits() + 1)
+    } else {
+        f64::from_bits(content.to_bits() - 1)
+    }
+}
+
+/// Largest float less than `content`.
+fn next_down(content: f64) -> f64 {
+    -next_up(-content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::ops::*;
+
+    fn schema() -> Schema {
+        Schema::new()
+            .set("age", SchemaType::Int, false)
+            .set("country", SchemaType::Str, false)
+            .set("score", SchemaType::Float, false)
+            .set("beta", SchemaType::Bool, true)
+    }
+
+    #[test]
+    fn it_generates_satisfying_and_falsifying_contexts() {
+        let expression = and([gt(get("age"), int(17)), eq(get("country"), str("DE"))]);
+
+        let examples = generate_examples(expression.as_ref(), &schema());
+        let ages = |contexts: &[Context]| -> Vec<Value> {
+            contexts
+                .iter()
+                .map(|context| context.get("age").unwrap().clone())
+                .collect()
+        };
+
+        assert!(!examples.satisfying().is_empty());
+        assert!(!examples.falsifying().is_empty());
+
+        for context in examples.satisfying() {
+            assert!(expression.eval_bool(context).unwrap());
+        }
+
+        for context in examples.falsifying() {
+            assert!(!expression.eval_bool(context).unwrap());
+        }
+
+        for context in examples.satisfying().iter().chain(examples.falsifying()) {
+            assert_eq!(context.get("score"), Some(&Value::Float(0.0)));
+            assert!(!context.contains_key("beta"));
+        }
+
+        assert!(ages(examples.satisfying()).contains(&Value::Int(18)));
+        assert!(ages(examples.falsifying()).contains(&Value::Int(17)));
+        assert!(ages(examples.falsifying()).contains(&Value::Int(16)));
+    }
+
+    #[test]
+    fn it_includes_float_boundaries() {
+        let expression = or([gt(get("score"), float(0.5)), get("beta")]);
+
+        let examples = generate_examples(expression.as_ref(), &schema());
+        let scores: Vec<_> = examples
+            .satisfying()
+            .iter()
+            .filter(|context| context.get("beta") == Some(&Value::Bool(false)))
+            .map(|context| context.get("score").unwrap().clone())
+            .collect();
+
+        assert!(scores.contains(&Value::Float(next_up(0.5))));
+        assert!(examples
+            .falsifying()
+            .iter()
+            .any(|context| context.get("score") == Some(&Value::Float(0.5))));
+    }
+
+    #[test]
+    fn it_steps_to_neighbouring_floats() {
+        assert_eq!(next_up(0.0), f64::from_bits(1));
+        assert_eq!(next_down(0.0), -f64::from_bits(1));
+        assert_eq!(next_up(-f64::from_bits(1)), -0.0);
+        assert!(next_up(1.0) > 1.0 && next_down(1.0) < 1.0);
+        assert_eq!(next_up(f64::MAX), f64::INFINITY);
+        assert_eq!(next_up(f64::INFINITY), f64::INFINITY);
+    }
+
+    #[test]
+    fn it_drops_literals_of_another_type_than_the_schema() {
+        let expression = eq(get("age"), str("18"));
+
+        let examples = generate_examples(expression.as_ref(), &schema());
+
+        assert!(examples.satisfying().is_empty());
+
+        for context in examples.falsifying() {
+            assert!(matches!(context.get("age"), Some(Value::Int(_))));
+        }
+    }
+
+    #[test]
+    fn it_generates_no_satisfying_contexts_for_contradictions() {
+        let expression = and([eq(get("age"), int(1)), eq(get("age"), int(2))]);
+
+        let examples = generate_examples(expression.as_ref(), &schema());
+
+        assert!(examples.satisfying().is_empty());
+        assert!(!examples.falsifying().is_empty());
+    }
+}
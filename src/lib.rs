@@ -1,13 +1,46 @@
+mod analysis;
+mod budget;
 mod context;
 mod expression;
+mod generate;
+mod lint;
 mod parser;
+mod program;
+mod refactor;
+mod render;
+mod schema;
 
-pub use context::Context;
+#[cfg(all(test, any(feature = "derive", feature = "macros")))]
+extern crate self as jet;
+
+pub use analysis::{implication, overlap, satisfiability, Implication, Satisfiability};
+pub use budget::{CancellationToken, EvalBudget};
+pub use context::{
+    AsyncContextSource, Context, ContextError, ContextErrorKind, ContextResult, ContextSource,
+    ResolverError,
+};
 pub use expression::eval_type::Type;
 pub use expression::ops;
+pub use expression::trace::{Trace, TraceOutcome};
+pub use expression::typed;
 pub use expression::value::Value;
-pub use expression::{EvalError, EvalResult, Expression};
-pub use parser::{parse, parse_json_value, ParserError, ParserResult};
+pub use expression::visit::{Folder, Visitor};
+pub use expression::{BoxFuture, EvalError, EvalErrorKind, EvalResult, Expression};
+pub use generate::{generate_examples, Examples};
+pub use lint::{lint, Lint, LintCode, Severity};
+pub use parser::{
+    parse, parse_json_value, parse_json_value_with_options, parse_with_options, ParseOptions,
+    ParserError, ParserErrorKind, ParserResult,
+};
+pub use program::{Frame, Program};
+pub use refactor::{find_key_usages, rename_key, KeyUsage, RenameKeyError};
+pub use render::{Renderer, Template};
+pub use schema::{context_path, ContextField, JetContext, Schema, SchemaField, SchemaType};
+
+#[cfg(feature = "macros")]
+pub use jet_macros::jet;
+#[cfg(feature = "derive")]
+pub use jet_macros::JetContext;
 
 #[cfg(test)]
 mod tests {
@@ -23,4 +56,52 @@ mod tests {
 
         assert_eq!(expression.eval(&context).unwrap(), Value::Bool(true));
     }
+
+    #[test]
+    fn it_shares_expressions_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Box<dyn Expression>>();
+        assert_send_sync::<Context>();
+
+        let expression: std::sync::Arc<Box<dyn Expression>> =
+            std::sync::Arc::new(parse(r#"{"gt": [{"get": ["age"]}, 17]}"#).unwrap());
+        let context = Context::new().set_int("age", 18);
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let expression = expression.clone();
+                let context = context.clone();
+                std::thread::spawn(move || expression.eval_bool(&context).unwrap())
+            })
+            .collect();
+
+        assert!(handles.into_iter().all(|handle| handle.join().unwrap()));
+    }
+
+    #[cfg(feature = "macros")]
+    #[test]
+    fn it_builds_expressions_with_jet_macro() {
+        use crate::ops::*;
+
+        let expression = jet!(userId == 1 && (age >= 18 || roles == ["admin"]) && !banned);
+        let expected = and(vec![
+            eq(get("userId"), int(1)),
+            or(vec![
+                not(gt(int(18), get("age"))),
+                eq(get("roles"), str_array(["admin"])),
+            ]),
+            not(get("banned")),
+        ]);
+        let context = Context::new()
+            .set_int("userId", 1)
+            .set_int("age", 18)
+            .set_str_array("roles", ["dev".to_string()])
+            .set_bool("banned", false);
+
+        assert_eq!(&expression, &expected);
+        assert!(expression.eval_bool(&context).unwrap());
+        assert!(!jet!(age < 18 || user.score <= -0.5)
+            .eval_bool(&context.set_float("user.score", 0.0))
+            .unwrap());
+    }
 }
@@ -0,0 +1,447 @@
+use crate::context::Context;
+use crate::expression::ops::get_name;
+use crate::expression::value::Value;
+use crate::expression::visit::Visitor;
+use crate::expression::Expression;
+use serde_json::{json, Value as JsonValue};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Severity {
+    /// Probably not what the author meant, but the rule still works as written.
+    Warning,
+    /// The rule, or part of it, can never be true.
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LintCode {
+    /// A subtree which evaluates to the same value in every context.
+    ConstantResult,
+    /// `eq` or `gt` with the same expression on both sides.
+    SelfComparison,
+    /// `and` with conditions on one key which cannot all hold, e.g. `x == 1 && x == 2`.
+    Contradiction,
+    /// `eq` with a float literal, which is sensitive to rounding.
+    FloatEquality,
+}
+
+impl LintCode {
+    /// Stable name of the rule, e.g. `self-comparison`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LintCode::ConstantResult => "constant-result",
+            LintCode::SelfComparison => "self-comparison",
+            LintCode::Contradiction => "contradiction",
+            LintCode::FloatEquality => "float-equality",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            LintCode::Contradiction => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for LintCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A suspicious pattern found by `lint()`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Lint {
+    code: LintCode,
+    pointer: String,
+    message: String,
+}
+
+impl Lint {
+    pub fn code(&self) -> LintCode {
+        self.code
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.code.severity()
+    }
+
+    /// JSON Pointer of the offending node within the expression's `to_json()`.
+    pub fn pointer(&self) -> &str {
+        &self.pointer
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        json!({
+            "code": self.code.as_str(),
+            "severity": self.severity().as_str(),
+            "pointer": self.pointer,
+            "message": self.message,
+        })
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} [{}] at '{}': {}",
+            self.severity(),
+            self.code,
+            self.pointer,
+            self.message
+        )
+    }
+}
+
+/// Suspicious patterns in `expression`, in the order their nodes are visited. Constant
+/// subtrees are reported once, without looking inside them.
+pub fn lint(expression: &dyn Expression) -> Vec<Lint> {
+    let mut linter = Linter {
+        root: expression,
+        parents: Vec::new(),
+        lints: Vec::new(),
+    };
+
+    expression.walk(&mut linter);
+    linter.lints
+}
+
+struct Linter<'a> {
+    root: &'a dyn Expression,
+    /// Names of the nodes above the one being visited.
+    parents: Vec<String>,
+    lints: Vec<Lint>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, code: LintCode, path: &[usize], message: String) {
+        self.lints.push(Lint {
+            code,
+            pointer: self.root.json_pointer(path).unwrap_or_default(),
+            message,
+        });
+    }
+}
+
+impl Visitor for Linter<'_> {
+    fn enter(&mut self, expression: &dyn Expression, path: &[usize]) -> bool {
+        if expression.as_value().is_some() {
+            return false;
+        }
+
+        if let Some(value) = constant_value(expression) {
+            self.report(
+                LintCode::ConstantResult,
+                path,
+                format!("always evaluates to {}", value.to_json()),
+            );
+            return false;
+        }
+
+        match expression.name() {
+            "and" | "or" => {
+                let on = expression.name() == "or";
+                let deciding = expression
+                    .args()
+                    .into_iter()
+                    .any(|arg| constant_value(arg.as_ref()) == Some(Value::Bool(on)));
+
+                if deciding {
+                    self.report(
+                        LintCode::ConstantResult,
+                        path,
+                        format!("has an arg which is always {}, so it is never {}", on, !on),
+                    );
+                }
+
+                // Conditions of nested `and`s are checked with the outermost one.
+                let nested = self.parents.last().is_some_and(|parent| parent == "and");
+
+                if !on && !nested {
+                    for message in contradictions(expression) {
+                        self.report(LintCode::Contradiction, path, message);
+                    }
+                }
+            }
+            "eq" | "gt" => {
+                let args = expression.args();
+
+                if args.len() == 2 && args[0] == args[1] {
+                    let outcome = match expression.name() {
+                        "eq" => "true unless it is NaN",
+                        _ => "false",
+                    };
+                    self.report(
+                        LintCode::SelfComparison,
+                        path,
+                        format!(
+                            "compares {} with itself, so it is always {}",
+                            args[0].to_json(),
+                            outcome
+                        ),
+                    );
+                }
+
+                let floats = args.iter().any(|arg| {
+                    matches!(arg.as_value(), Some(Value::Float(_) | Value::FloatArray(_)))
+                });
+
+                if expression.name() == "eq" && floats {
+                    self.report(
+                        LintCode::FloatEquality,
+                        path,
+                        "compares floats for exact equality, which rounding can break".to_string(),
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        self.parents.push(expression.name().to_string());
+        true
+    }
+
+    fn leave(&mut self, _expression: &dyn Expression, _path: &[usize]) {
+        self.parents.pop();
+    }
+}
+
+/// Value of a non-literal expression which does not read the context.
+fn constant_value(expression: &dyn Expression) -> Option<Value> {
+    if expression.as_value().is_some() || expression.context_dependencies().is_some() {
+        return None;
+    }
+
+    expression.eval(&Context::new()).ok()
+}
+
+/// A condition on a single context key, e.g. `x > 1`.
+#[derive(Clone, Debug)]
+enum Condition {
+    Eq(Value),
+    Gt(Value),
+    Lt(Value),
+}
+
+impl Condition {
+    fn describe(&self, name: &str) -> String {
+        match self {
+            Condition::Eq(value) => format!("{} == {}", name, value.to_json()),
+            Condition::Gt(value) => format!("{} > {}", name, value.to_json()),
+            Condition::Lt(value) => format!("{} < {}", name, value.to_json()),
+        }
+    }
+
+    fn conflicts_with(&self, other: &Condition) -> bool {
+        use Condition::*;
+
+        match (self, other) {
+            (Eq(value), Eq(other)) => comparable(value, other) && value != other,
+            (Eq(value), Gt(bound)) | (Gt(bound), Eq(value)) => {
+                comparable(value, bound) && value.partial_cmp(bound) != Some(Ordering::Greater)
+            }
+            (Eq(value), Lt(bound)) | (Lt(bound), Eq(value)) => {
+                comparable(value, bound) && value.partial_cmp(bound) != Some(Ordering::Less)
+            }
+            (Gt(lower), Lt(upper)) | (Lt(upper), Gt(lower)) => {
+                comparable(lower, upper) && nothing_between(lower, upper)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn comparable(left: &Value, right: &Value) -> bool {
+    left.concrete_type() == right.concrete_type() && left.partial_cmp(right).is_some()
+}
+
+/// Whether no value is strictly greater than `lower` and strictly less than `upper`.
+fn nothing_between(lower: &Value, upper: &Value) -> bool {
+    match (lower, upper) {
+        (Value::Int(lower), Value::Int(upper)) => *upper <= lower.saturating_add(1),
+        (Value::Bool(_), Value::Bool(_)) => true,
+        _ => upper.partial_cmp(lower) != Some(Ordering::Greater),
+    }
+}
+
+/// Messages for every pair of conditions of an `and`, including nested ones, which no
+/// value of their key can satisfy at once.
+fn contradictions(expression: &dyn Expression) -> Vec<String> {
+    let mut conditions: BTreeMap<String, Vec<Condition>> = BTreeMap::new();
+    collect_conditions(expression, &mut conditions);
+
+    let mut messages = Vec::new();
+
+    for (name, conditions) in &conditions {
+        for (position, condition) in conditions.iter().enumerate() {
+            for other in &conditions[position + 1..] {
+                if condition.conflicts_with(other) {
+                    messages.push(format!(
+                        "`{}` and `{}` cannot both be true",
+                        condition.describe(name),
+                        other.describe(name)
+                    ));
+                }
+            }
+        }
+    }
+
+    messages
+}
+
+fn collect_conditions(
+    expression: &dyn Expression,
+    conditions: &mut BTreeMap<String, Vec<Condition>>,
+) {
+    for arg in expression.args() {
+        let args = arg.args();
+
+        match (arg.name(), args.as_slice()) {
+            ("and", _) if arg.as_value().is_none() => collect_conditions(arg.as_ref(), conditions),
+            (op @ ("eq" | "gt"), [left, right]) if arg.as_value().is_none() => {
+                let (name, value, flipped) = match (get_name(left.as_ref()), right.as_value()) {
+                    (Some(name), Some(value)) => (name, value, false),
+                    _ => match (left.as_value(), get_name(right.as_ref())) {
+                        (Some(value), Some(name)) => (name, value, true),
+                        _ => continue,
+                    },
+                };
+
+                let condition = match (op, flipped) {
+                    ("eq", _) => Condition::Eq(value.clone()),
+                    (_, false) => Condition::Gt(value.clone()),
+                    (_, true) => Condition::Lt(value.clone()),
+                };
+
+                conditions.entry(name).or_default().push(condition);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::ops::*;
+    use crate::parser::parse;
+
+    fn codes(expression: &dyn Expression) -> Vec<(LintCode, String)> {
+        lint(expression)
+            .into_iter()
+            .map(|lint| (lint.code(), lint.pointer().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn it_reports_self_comparisons_and_float_equality() {
+        let expression =
+            parse(r#"{"or": [{"gt": [{"get": ["a"]}, {"get": ["a"]}]}, {"eq": [{"get": ["score"]}, 0.1]}]}"#)
+                .unwrap();
+
+        let lints = lint(expression.as_ref());
+
+        assert_eq!(
+            codes(expression.as_ref()),
+            [
+                (LintCode::SelfComparison, "/or/0".to_string()),
+                (LintCode::FloatEquality, "/or/1".to_string()),
+            ]
+        );
+        assert_eq!(lints[0].severity(), Severity::Warning);
+        assert_eq!(
+            lints[0].to_string(),
+            r#"warning [self-comparison] at '/or/0': compares {"get":["a"]} with itself, so it is always false"#
+        );
+    }
+
+    #[test]
+    fn it_reports_constant_results() {
+        let expression = and([
+            get("beta"),
+            or([gt(int(2), int(1)), get("admin")]),
+            not(bool(true)),
+        ]);
+
+        assert_eq!(
+            codes(expression.as_ref()),
+            [
+                (LintCode::ConstantResult, "".to_string()),
+                (LintCode::ConstantResult, "/and/1".to_string()),
+                (LintCode::ConstantResult, "/and/1/or/0".to_string()),
+                (LintCode::ConstantResult, "/and/2".to_string()),
+            ]
+        );
+        assert!(lint(eq(get("plan"), str("pro")).as_ref()).is_empty());
+    }
+
+    #[test]
+    fn it_reports_contradictions() {
+        let expression = or([
+            and([eq(get("x"), int(1)), eq(int(2), get("x"))]),
+            and([
+                gt(get("age"), int(17)),
+                and([gt(int(18), get("age")), eq(get("plan"), str("pro"))]),
+            ]),
+            and([gt(get("age"), int(17)), gt(int(20), get("age"))]),
+            and([
+                eq(get("y"), int(1)),
+                and([eq(get("y"), int(2)), and([eq(get("z"), get("z"))])]),
+                or([and([gt(get("z"), int(1)), gt(int(0), get("z"))])]),
+            ]),
+        ]);
+
+        let lints = lint(expression.as_ref());
+
+        assert_eq!(
+            codes(expression.as_ref()),
+            [
+                (LintCode::Contradiction, "/or/0".to_string()),
+                (LintCode::Contradiction, "/or/1".to_string()),
+                (LintCode::Contradiction, "/or/3".to_string()),
+                (
+                    LintCode::SelfComparison,
+                    "/or/3/and/1/and/1/and/0".to_string()
+                ),
+                (LintCode::Contradiction, "/or/3/and/2/or/0".to_string()),
+            ]
+        );
+        assert_eq!(lints[0].severity(), Severity::Error);
+        assert_eq!(
+            lints[0].message(),
+            "`x == 1` and `x == 2` cannot both be true"
+        );
+        assert_eq!(
+            lints[1].message(),
+            "`age > 17` and `age < 18` cannot both be true"
+        );
+        assert_eq!(
+            lints[3].message(),
+            r#"compares {"get":["z"]} with itself, so it is always true unless it is NaN"#
+        );
+    }
+}
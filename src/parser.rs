@@ -1,6 +1,7 @@
 use crate::expression::ops::*;
 use crate::expression::Expression;
 use serde_json::{Error as JsonError, Map as JsonMap, Number as JsonNumber, Value as JsonValue};
+use std::fmt;
 
 type JsonObject = JsonMap<String, JsonValue>;
 
@@ -8,43 +9,358 @@ pub type ParserResult<T> = Result<T, ParserError>;
 
 #[derive(Debug)]
 pub struct ParserError {
-    errorKind: ParserErrorKind,
+    error_kind: ParserErrorKind,
     json: Option<JsonValue>,
 }
 
 #[derive(Debug)]
-enum ParserErrorKind {
+pub enum ParserErrorKind {
     InvalidInput(JsonError),
     InvalidNumber,
+    UnsupportedNull,
     EmptyArray,
     MixedArray,
     NestedArray,
     InvalidOp,
     UnknownOp,
+    MaxDepthExceeded { max_depth: usize },
+    MaxNodesExceeded { max_nodes: usize },
+    MaxStringLengthExceeded { max_string_length: usize },
+    MaxArrayLengthExceeded { max_array_length: usize },
+    MaxInputLengthExceeded { max_input_length: usize },
+}
+
+impl ParserError {
+    pub fn kind(&self) -> &ParserErrorKind {
+        &self.error_kind
+    }
+
+    pub fn json(&self) -> Option<&JsonValue> {
+        self.json.as_ref()
+    }
+}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error_kind {
+            ParserErrorKind::InvalidInput(err) => write!(f, "invalid JSON input: {}", err)?,
+            ParserErrorKind::InvalidNumber => write!(f, "invalid number")?,
+            ParserErrorKind::UnsupportedNull => write!(f, "null is not supported")?,
+            ParserErrorKind::EmptyArray => write!(f, "empty array")?,
+            ParserErrorKind::MixedArray => write!(f, "array items have different types")?,
+            ParserErrorKind::NestedArray => write!(f, "array items must be scalars")?,
+            ParserErrorKind::InvalidOp => write!(f, "invalid op")?,
+            ParserErrorKind::UnknownOp => write!(f, "unknown op")?,
+            ParserErrorKind::MaxDepthExceeded { max_depth } => {
+                write!(f, "nesting depth exceeds {}", max_depth)?
+            }
+            ParserErrorKind::MaxNodesExceeded { max_nodes } => {
+                write!(f, "node count exceeds {}", max_nodes)?
+            }
+            ParserErrorKind::MaxStringLengthExceeded { max_string_length } => {
+                write!(f, "string length exceeds {}", max_string_length)?
+            }
+            ParserErrorKind::MaxArrayLengthExceeded { max_array_length } => {
+                write!(f, "array length exceeds {}", max_array_length)?
+            }
+            ParserErrorKind::MaxInputLengthExceeded { max_input_length } => {
+                write!(f, "input length exceeds {}", max_input_length)?
+            }
+        }
+
+        match &self.json {
+            Some(json) => write!(f, " in {}", json),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for ParserError {}
+
+/// Limits enforced while parsing, so that untrusted input cannot exhaust
+/// the stack or memory. String and input length are measured in bytes.
+///
+/// Only the input length is checked before serde_json reads the input. The other
+/// limits are checked on the JSON value it builds, so callers of `parse_json_value()`
+/// have to bound the size of that value themselves.
+#[derive(Clone, Debug)]
+pub struct ParseOptions {
+    max_depth: usize,
+    max_nodes: usize,
+    max_string_length: usize,
+    max_array_length: usize,
+    max_input_length: usize,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_max_depth(self, max_depth: usize) -> Self {
+        ParseOptions { max_depth, ..self }
+    }
+
+    pub fn set_max_nodes(self, max_nodes: usize) -> Self {
+        ParseOptions { max_nodes, ..self }
+    }
+
+    pub fn set_max_string_length(self, max_string_length: usize) -> Self {
+        ParseOptions {
+            max_string_length,
+            ..self
+        }
+    }
+
+    pub fn set_max_array_length(self, max_array_length: usize) -> Self {
+        ParseOptions {
+            max_array_length,
+            ..self
+        }
+    }
+
+    pub fn set_max_input_length(self, max_input_length: usize) -> Self {
+        ParseOptions {
+            max_input_length,
+            ..self
+        }
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 64,
+            max_nodes: 10_000,
+            max_string_length: 64 * 1024,
+            max_array_length: 10_000,
+            max_input_length: 1024 * 1024,
+        }
+    }
 }
 
 pub fn parse(input: &str) -> ParserResult<Box<dyn Expression>> {
+    parse_with_options(input, &ParseOptions::default())
+}
+
+pub fn parse_with_options(
+    input: &str,
+    options: &ParseOptions,
+) -> ParserResult<Box<dyn Expression>> {
+    if input.len() > options.max_input_length {
+        return Err(ParserError {
+            error_kind: ParserErrorKind::MaxInputLengthExceeded {
+                max_input_length: options.max_input_length,
+            },
+            json: None,
+        });
+    }
+
     let json = match serde_json::from_str(input) {
         Ok(json) => json,
         Err(err) => {
             return Err(ParserError {
-                errorKind: ParserErrorKind::InvalidInput(err),
+                error_kind: ParserErrorKind::InvalidInput(err),
                 json: None,
             })
         }
     };
 
-    parse_json_value(&json)
+    parse_json_value_with_options(&json, options)
 }
 
 pub fn parse_json_value(json: &JsonValue) -> ParserResult<Box<dyn Expression>> {
-    match json {
-        JsonValue::Bool(content) => Ok(bool(*content)),
-        JsonValue::Number(content) => parse_json_number(content),
-        JsonValue::String(content) => Ok(str(content)),
-        JsonValue::Array(content) => parse_json_array(content),
-        JsonValue::Object(content) => parse_json_object(content),
-        _ => panic!("unsupported"),
+    parse_json_value_with_options(json, &ParseOptions::default())
+}
+
+pub fn parse_json_value_with_options(
+    json: &JsonValue,
+    options: &ParseOptions,
+) -> ParserResult<Box<dyn Expression>> {
+    let mut parser = Parser {
+        options,
+        depth: 0,
+        nodes: 0,
+    };
+
+    parser.parse_json_value(json)
+}
+
+struct Parser<'a> {
+    options: &'a ParseOptions,
+    depth: usize,
+    nodes: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn parse_json_value(&mut self, json: &JsonValue) -> ParserResult<Box<dyn Expression>> {
+        self.nodes += 1;
+
+        if self.nodes > self.options.max_nodes {
+            return Err(ParserError {
+                error_kind: ParserErrorKind::MaxNodesExceeded {
+                    max_nodes: self.options.max_nodes,
+                },
+                json: None,
+            });
+        }
+
+        match json {
+            JsonValue::Null => Err(ParserError {
+                error_kind: ParserErrorKind::UnsupportedNull,
+                json: Some(JsonValue::Null),
+            }),
+            JsonValue::Bool(content) => Ok(bool(*content)),
+            JsonValue::Number(content) => parse_json_number(content),
+            JsonValue::String(content) => {
+                self.check_string_length(content)?;
+                Ok(str(content))
+            }
+            JsonValue::Array(content) => self.parse_json_array(content),
+            JsonValue::Object(content) => {
+                if self.depth >= self.options.max_depth {
+                    return Err(ParserError {
+                        error_kind: ParserErrorKind::MaxDepthExceeded {
+                            max_depth: self.options.max_depth,
+                        },
+                        json: None,
+                    });
+                }
+
+                self.depth += 1;
+                let result = self.parse_json_object(content);
+                self.depth -= 1;
+                result
+            }
+        }
+    }
+
+    fn parse_json_values(
+        &mut self,
+        content: &[JsonValue],
+    ) -> ParserResult<Vec<Box<dyn Expression>>> {
+        content
+            .iter()
+            .map(|json_value| self.parse_json_value(json_value))
+            .collect()
+    }
+
+    fn check_string_length(&self, content: &str) -> ParserResult<()> {
+        if content.len() > self.options.max_string_length {
+            return Err(ParserError {
+                error_kind: ParserErrorKind::MaxStringLengthExceeded {
+                    max_string_length: self.options.max_string_length,
+                },
+                json: None,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn parse_json_array(&self, content: &[JsonValue]) -> ParserResult<Box<dyn Expression>> {
+        if content.is_empty() {
+            return Err(ParserError {
+                error_kind: ParserErrorKind::EmptyArray,
+                json: Some(JsonValue::Array(content.to_vec())),
+            });
+        }
+
+        if content.len() > self.options.max_array_length {
+            return Err(ParserError {
+                error_kind: ParserErrorKind::MaxArrayLengthExceeded {
+                    max_array_length: self.options.max_array_length,
+                },
+                json: None,
+            });
+        }
+
+        match &content[0] {
+            JsonValue::Bool(_) => parse_json_bool_array(content),
+            JsonValue::Number(value) => {
+                if value.is_f64() {
+                    parse_json_float_array(content)
+                } else {
+                    parse_json_int_array(content)
+                }
+            }
+            JsonValue::String(_) => self.parse_json_str_array(content),
+            _ => Err(ParserError {
+                error_kind: ParserErrorKind::NestedArray,
+                json: Some(JsonValue::Array(content.to_vec())),
+            }),
+        }
+    }
+
+    fn parse_json_str_array(&self, content: &[JsonValue]) -> ParserResult<Box<dyn Expression>> {
+        let mut items: Vec<String> = Vec::new();
+
+        for json_value in content {
+            match json_value {
+                JsonValue::String(value) => {
+                    self.check_string_length(value)?;
+                    items.push(value.clone())
+                }
+                _ => {
+                    return Err(ParserError {
+                        error_kind: ParserErrorKind::MixedArray,
+                        json: Some(JsonValue::Array(content.to_vec())),
+                    })
+                }
+            }
+        }
+
+        Ok(str_array(items))
+    }
+
+    fn parse_json_object(&mut self, object: &JsonObject) -> ParserResult<Box<dyn Expression>> {
+        let mut entries = object.iter().map(|(k, v)| (k.as_str(), v));
+
+        let entry = match (entries.next(), entries.next()) {
+            (Some(entry), None) => entry,
+            _ => {
+                return Err(ParserError {
+                    error_kind: ParserErrorKind::InvalidOp,
+                    json: Some(JsonValue::Object(object.clone())),
+                })
+            }
+        };
+
+        match entry {
+            ("get", JsonValue::Array(content)) if content.len() == 1 => match &content[0] {
+                JsonValue::String(name) => {
+                    self.check_string_length(name)?;
+                    Ok(get(name))
+                }
+                _ => Err(ParserError {
+                    error_kind: ParserErrorKind::InvalidOp,
+                    json: Some(JsonValue::Object(object.clone())),
+                }),
+            },
+            ("eq", JsonValue::Array(content)) if content.len() == 2 => {
+                let left = self.parse_json_value(&content[0])?;
+                let right = self.parse_json_value(&content[1])?;
+                Ok(eq(left, right))
+            }
+            ("gt", JsonValue::Array(content)) if content.len() == 2 => {
+                let left = self.parse_json_value(&content[0])?;
+                let right = self.parse_json_value(&content[1])?;
+                Ok(gt(left, right))
+            }
+            ("and", JsonValue::Array(content)) if !content.is_empty() => {
+                Ok(and(self.parse_json_values(content)?))
+            }
+            ("or", JsonValue::Array(content)) if !content.is_empty() => {
+                Ok(or(self.parse_json_values(content)?))
+            }
+            ("not", JsonValue::Array(content)) if content.len() == 1 => {
+                Ok(not(self.parse_json_value(&content[0])?))
+            }
+            _ => Err(ParserError {
+                error_kind: ParserErrorKind::UnknownOp,
+                json: Some(JsonValue::Object(object.clone())),
+            }),
+        }
     }
 }
 
@@ -62,7 +378,7 @@ fn json_number_as_f64(number: &JsonNumber) -> ParserResult<f64> {
     match number.as_f64() {
         Some(value) => Ok(value),
         None => Err(ParserError {
-            errorKind: ParserErrorKind::InvalidNumber,
+            error_kind: ParserErrorKind::InvalidNumber,
             json: Some(JsonValue::Number(number.clone())),
         }),
     }
@@ -72,38 +388,13 @@ fn json_number_as_i64(number: &JsonNumber) -> ParserResult<i64> {
     match number.as_i64() {
         Some(value) => Ok(value),
         None => Err(ParserError {
-            errorKind: ParserErrorKind::InvalidNumber,
+            error_kind: ParserErrorKind::InvalidNumber,
             json: Some(JsonValue::Number(number.clone())),
         }),
     }
 }
 
-fn parse_json_array(content: &Vec<JsonValue>) -> ParserResult<Box<dyn Expression>> {
-    if content.len() == 0 {
-        return Err(ParserError {
-            errorKind: ParserErrorKind::EmptyArray,
-            json: Some(JsonValue::Array(content.clone())),
-        });
-    }
-
-    match &content[0] {
-        JsonValue::Bool(_) => parse_json_bool_array(&content),
-        JsonValue::Number(value) => {
-            if value.is_f64() {
-                parse_json_float_array(&content)
-            } else {
-                parse_json_int_array(&content)
-            }
-        }
-        JsonValue::String(_) => parse_json_str_array(&content),
-        _ => Err(ParserError {
-            errorKind: ParserErrorKind::NestedArray,
-            json: Some(JsonValue::Array(content.clone())),
-        }),
-    }
-}
-
-fn parse_json_bool_array(content: &Vec<JsonValue>) -> ParserResult<Box<dyn Expression>> {
+fn parse_json_bool_array(content: &[JsonValue]) -> ParserResult<Box<dyn Expression>> {
     let mut items: Vec<bool> = Vec::new();
 
     for json_value in content {
@@ -111,8 +402,8 @@ fn parse_json_bool_array(content: &Vec<JsonValue>) -> ParserResult<Box<dyn Expre
             JsonValue::Bool(value) => items.push(*value),
             _ => {
                 return Err(ParserError {
-                    errorKind: ParserErrorKind::MixedArray,
-                    json: Some(JsonValue::Array(content.clone())),
+                    error_kind: ParserErrorKind::MixedArray,
+                    json: Some(JsonValue::Array(content.to_vec())),
                 })
             }
         }
@@ -121,16 +412,16 @@ fn parse_json_bool_array(content: &Vec<JsonValue>) -> ParserResult<Box<dyn Expre
     Ok(bool_array(items))
 }
 
-fn parse_json_float_array(content: &Vec<JsonValue>) -> ParserResult<Box<dyn Expression>> {
+fn parse_json_float_array(content: &[JsonValue]) -> ParserResult<Box<dyn Expression>> {
     let mut items: Vec<f64> = Vec::new();
 
     for json_value in content {
         match json_value {
-            JsonValue::Number(value) if value.is_f64() => items.push(value.as_f64().unwrap()),
+            JsonValue::Number(value) if value.is_f64() => items.push(json_number_as_f64(value)?),
             _ => {
                 return Err(ParserError {
-                    errorKind: ParserErrorKind::MixedArray,
-                    json: Some(JsonValue::Array(content.clone())),
+                    error_kind: ParserErrorKind::MixedArray,
+                    json: Some(JsonValue::Array(content.to_vec())),
                 })
             }
         }
@@ -139,16 +430,16 @@ fn parse_json_float_array(content: &Vec<JsonValue>) -> ParserResult<Box<dyn Expr
     Ok(float_array(items))
 }
 
-fn parse_json_int_array(content: &Vec<JsonValue>) -> ParserResult<Box<dyn Expression>> {
+fn parse_json_int_array(content: &[JsonValue]) -> ParserResult<Box<dyn Expression>> {
     let mut items: Vec<i64> = Vec::new();
 
     for json_value in content {
         match json_value {
-            JsonValue::Number(value) if value.is_i64() => items.push(value.as_i64().unwrap()),
+            JsonValue::Number(value) if value.is_i64() => items.push(json_number_as_i64(value)?),
             _ => {
                 return Err(ParserError {
-                    errorKind: ParserErrorKind::MixedArray,
-                    json: Some(JsonValue::Array(content.clone())),
+                    error_kind: ParserErrorKind::MixedArray,
+                    json: Some(JsonValue::Array(content.to_vec())),
                 })
             }
         }
@@ -157,57 +448,6 @@ fn parse_json_int_array(content: &Vec<JsonValue>) -> ParserResult<Box<dyn Expres
     Ok(int_array(items))
 }
 
-fn parse_json_str_array(content: &Vec<JsonValue>) -> ParserResult<Box<dyn Expression>> {
-    let mut items: Vec<String> = Vec::new();
-
-    for json_value in content {
-        match json_value {
-            JsonValue::String(value) => items.push(value.clone()),
-            _ => {
-                return Err(ParserError {
-                    errorKind: ParserErrorKind::MixedArray,
-                    json: Some(JsonValue::Array(content.clone())),
-                })
-            }
-        }
-    }
-
-    Ok(str_array(items))
-}
-
-fn parse_json_object(object: &JsonObject) -> ParserResult<Box<dyn Expression>> {
-    if object.keys().count() > 1 {
-        return Err(ParserError {
-            errorKind: ParserErrorKind::InvalidOp,
-            json: Some(JsonValue::Object(object.clone())),
-        });
-    }
-
-    match object.iter().map(|(k, v)| (k.as_str(), v)).next().unwrap() {
-        ("get", JsonValue::Array(content)) if content.len() == 1 => match &content[0] {
-            JsonValue::String(name) => Ok(get(name)),
-            _ => Err(ParserError {
-                errorKind: ParserErrorKind::InvalidOp,
-                json: Some(JsonValue::Object(object.clone())),
-            }),
-        },
-        ("eq", JsonValue::Array(content)) if content.len() == 2 => {
-            let left = parse_json_value(&content[0])?;
-            let right = parse_json_value(&content[1])?;
-            Ok(eq(left, right))
-        }
-        ("gt", JsonValue::Array(content)) if content.len() == 2 => {
-            let left = parse_json_value(&content[0])?;
-            let right = parse_json_value(&content[1])?;
-            Ok(gt(left, right))
-        }
-        _ => Err(ParserError {
-            errorKind: ParserErrorKind::UnknownOp,
-            json: Some(JsonValue::Object(object.clone())),
-        }),
-    }
-}
-
 #[cfg(test)]
 pub mod test_utils {
     use super::*;
@@ -228,9 +468,122 @@ mod tests {
     use crate::parser::test_utils::*;
     use serde_json::json;
 
+    fn nested_eq(depth: usize) -> JsonValue {
+        (0..depth).fold(json!(true), |inner, _| json!({"eq": [inner, true]}))
+    }
+
     #[test]
     fn it_parses_json() {
         assert_parse_eq(json!({"eq": [1, {"get": ["userId"]}]}));
-        assert_parse_eq(json!(true))
+        assert_parse_eq(json!(true));
+        assert_parse_eq(json!({"and": [{"not": [true]}, {"or": [false, true, true]}]}))
+    }
+
+    #[test]
+    fn it_does_not_panic_on_unsupported_input() {
+        assert!(matches!(
+            parse_json_value(&json!(null)).unwrap_err().kind(),
+            ParserErrorKind::UnsupportedNull
+        ));
+        assert!(matches!(
+            parse_json_value(&json!({"eq": [null, 1]}))
+                .unwrap_err()
+                .kind(),
+            ParserErrorKind::UnsupportedNull
+        ));
+        assert!(matches!(
+            parse_json_value(&json!({})).unwrap_err().kind(),
+            ParserErrorKind::InvalidOp
+        ));
+        assert!(matches!(
+            parse_json_value(&json!([1.5, 2])).unwrap_err().kind(),
+            ParserErrorKind::MixedArray
+        ));
+    }
+
+    #[test]
+    fn it_limits_nesting_depth() {
+        let options = ParseOptions::new().set_max_depth(3);
+
+        assert!(parse_json_value_with_options(&nested_eq(3), &options).is_ok());
+        assert!(matches!(
+            parse_json_value_with_options(&nested_eq(4), &options)
+                .unwrap_err()
+                .kind(),
+            ParserErrorKind::MaxDepthExceeded { max_depth: 3 }
+        ));
+        assert!(matches!(
+            parse_json_value(&nested_eq(200)).unwrap_err().kind(),
+            ParserErrorKind::MaxDepthExceeded { max_depth: 64 }
+        ));
+    }
+
+    #[test]
+    fn it_limits_node_count() {
+        let options = ParseOptions::new().set_max_nodes(5);
+
+        assert!(parse_json_value_with_options(&nested_eq(2), &options).is_ok());
+        assert!(matches!(
+            parse_json_value_with_options(&nested_eq(3), &options)
+                .unwrap_err()
+                .kind(),
+            ParserErrorKind::MaxNodesExceeded { max_nodes: 5 }
+        ));
+    }
+
+    #[test]
+    fn it_limits_string_and_array_length() {
+        let options = ParseOptions::new()
+            .set_max_string_length(3)
+            .set_max_array_length(2);
+
+        assert!(parse_with_options(r#"{"eq": ["abc", ["a", "b"]]}"#, &options).is_ok());
+        assert!(matches!(
+            parse_with_options(r#""abcd""#, &options)
+                .unwrap_err()
+                .kind(),
+            ParserErrorKind::MaxStringLengthExceeded {
+                max_string_length: 3
+            }
+        ));
+        assert!(matches!(
+            parse_with_options(r#"{"get": ["abcd"]}"#, &options)
+                .unwrap_err()
+                .kind(),
+            ParserErrorKind::MaxStringLengthExceeded {
+                max_string_length: 3
+            }
+        ));
+        assert!(matches!(
+            parse_with_options(r#"["a", "abcd"]"#, &options)
+                .unwrap_err()
+                .kind(),
+            ParserErrorKind::MaxStringLengthExceeded {
+                max_string_length: 3
+            }
+        ));
+        assert!(matches!(
+            parse_with_options("[1, 2, 3]", &options)
+                .unwrap_err()
+                .kind(),
+            ParserErrorKind::MaxArrayLengthExceeded {
+                max_array_length: 2
+            }
+        ));
+    }
+
+    #[test]
+    fn it_limits_input_length_before_reading_json() {
+        let options = ParseOptions::new().set_max_input_length(8);
+
+        assert!(parse_with_options("[1, 2]", &options).is_ok());
+        assert!(matches!(
+            parse_with_options("[1, 2, 3, 4", &options)
+                .unwrap_err()
+                .kind(),
+            ParserErrorKind::MaxInputLengthExceeded {
+                max_input_length: 8
+            }
+        ));
     }
 }
@@ -0,0 +1,234 @@
+use crate::expression::ops::{get, get_name};
+use crate::expression::visit::Visitor;
+use crate::expression::Expression;
+use serde_json::Value as JsonValue;
+use std::fmt;
+
+/// A `get` in one of a set of expressions.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct KeyUsage {
+    expression: usize,
+    name: String,
+    pointer: String,
+}
+
+impl KeyUsage {
+    /// Position of the expression in the set which was searched.
+    pub fn expression(&self) -> usize {
+        self.expression
+    }
+
+    /// Context name read by the `get`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// JSON Pointer of the `get` within the expression's `to_json()`, e.g. `/and/1/eq/0`.
+    pub fn pointer(&self) -> &str {
+        &self.pointer
+    }
+}
+
+/// Every `get` of a literal name in `expressions`, in order.
+pub fn find_key_usages(expressions: &[Box<dyn Expression>]) -> Vec<KeyUsage> {
+    let mut usages = Vec::new();
+
+    for (position, expression) in expressions.iter().enumerate() {
+        expression.walk(&mut KeyUsageFinder {
+            root: expression.as_ref(),
+            expression: position,
+            usages: &mut usages,
+        });
+    }
+
+    usages
+}
+
+/// Usages of a key which `rename_key()` could not rewrite, because an op's `to_json()`
+/// does not keep its args at the usage's pointer.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RenameKeyError {
+    usages: Vec<KeyUsage>,
+}
+
+impl RenameKeyError {
+    pub fn usages(&self) -> &[KeyUsage] {
+        &self.usages
+    }
+}
+
+impl fmt::Display for RenameKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "could not rewrite {} key usages", self.usages.len())
+    }
+}
+
+impl std::error::Error for RenameKeyError {}
+
+/// `to_json()` of each expression with `from` renamed to `to` in every `get`. Names
+/// below `from` are renamed as well, so renaming `user` turns `user.id` into `account.id`
+/// but leaves `username` alone. Everything else keeps its structure.
+pub fn rename_key(
+    expressions: &[Box<dyn Expression>],
+    from: &str,
+    to: &str,
+) -> Result<Vec<JsonValue>, RenameKeyError> {
+    let mut rules: Vec<JsonValue> = expressions
+        .iter()
+        .map(|expression| expression.to_json())
+        .collect();
+    let mut skipped = Vec::new();
+
+    for usage in find_key_usages(expressions) {
+        let renamed = match usage.name.strip_prefix(from) {
+            Some("") => to.to_string(),
+            Some(rest) if rest.starts_with('.') => format!("{}{}", to, rest),
+            _ => continue,
+        };
+
+        match rules[usage.expression].pointer_mut(&usage.pointer) {
+            Some(json) if *json == get(&usage.name).to_json() => *json = get(renamed).to_json(),
+            _ => skipped.push(usage),
+        }
+    }
+
+    if skipped.is_empty() {
+        Ok(rules)
+    } else {
+        Err(RenameKeyError { usages: skipped })
+    }
+}
+
+struct KeyUsageFinder<'a> {
+    root: &'a dyn Expression,
+    expression: usize,
+    usages: &'a mut Vec<KeyUsage>,
+}
+
+impl Visitor for KeyUsageFinder<'_> {
+    fn enter(&mut self, expression: &dyn Expression, path: &[usize]) -> bool {
+        match get_name(expression) {
+            Some(name) => {
+                self.usages.push(KeyUsage {
+                    expression: self.expression,
+                    name,
+                    pointer: self.root.json_pointer(path).unwrap_or_default(),
+                });
+                false
+            }
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+    use crate::expression::eval_type::Type;
+    use crate::expression::ops::*;
+    use crate::expression::value::Value;
+    use crate::expression::{args_context_dependencies, EvalResult};
+    use crate::parser::parse;
+    use serde_json::json;
+
+    fn rules() -> Vec<Box<dyn Expression>> {
+        vec![
+            parse(r#"{"and": [{"eq": [{"get": ["userId"]}, 1]}, {"gt": [{"get": ["user.age"]}, 17]}]}"#)
+                .unwrap(),
+            parse(r#"{"or": [{"get": ["username"]}, {"not": [{"get": ["user"]}]}]}"#).unwrap(),
+            parse(r#"{"get": ["userId"]}"#).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn it_finds_key_usages() {
+        let usages: Vec<_> = find_key_usages(&rules())
+            .iter()
+            .map(|usage| {
+                (
+                    usage.expression(),
+                    usage.name().to_string(),
+                    usage.pointer().to_string(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            usages,
+            [
+                (0, "userId".to_string(), "/and/0/eq/0".to_string()),
+                (0, "user.age".to_string(), "/and/1/gt/0".to_string()),
+                (1, "username".to_string(), "/or/0".to_string()),
+                (1, "user".to_string(), "/or/1/not/0".to_string()),
+                (2, "userId".to_string(), "".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_renames_keys_and_prefixes() {
+        let rules = rules();
+
+        assert_eq!(
+            rename_key(&rules, "userId", "accountId").unwrap(),
+            [
+                json!({"and": [{"eq": [{"get": ["accountId"]}, 1]}, {"gt": [{"get": ["user.age"]}, 17]}]}),
+                json!({"or": [{"get": ["username"]}, {"not": [{"get": ["user"]}]}]}),
+                json!({"get": ["accountId"]}),
+            ]
+        );
+        assert_eq!(
+            rename_key(&rules, "user", "account").unwrap()[..2],
+            [
+                json!({"and": [{"eq": [{"get": ["userId"]}, 1]}, {"gt": [{"get": ["account.age"]}, 17]}]}),
+                json!({"or": [{"get": ["username"]}, {"not": [{"get": ["account"]}]}]}),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reports_usages_it_cannot_rewrite() {
+        let rules: Vec<Box<dyn Expression>> = vec![
+            Box::new(Flag { arg: get("userId") }),
+            eq(get("userId"), int(1)),
+        ];
+
+        let err = rename_key(&rules, "userId", "accountId").unwrap_err();
+
+        assert_eq!(err.usages(), &find_key_usages(&rules)[..1]);
+        assert_eq!(err.usages()[0].pointer(), "/flag/0");
+    }
+
+    /// Op whose `to_json()` does not list its args under its name.
+    #[derive(Clone)]
+    struct Flag {
+        arg: Box<dyn Expression>,
+    }
+
+    impl Expression for Flag {
+        fn eval(&self, context: &Context) -> EvalResult<Value> {
+            self.arg.eval(context)
+        }
+
+        fn eval_type(&self, _context: &Context) -> EvalResult<Type> {
+            Ok(Type::Bool)
+        }
+
+        fn context_dependencies(&self) -> Option<Vec<String>> {
+            args_context_dependencies(self)
+        }
+
+        fn name(&self) -> &str {
+            "flag"
+        }
+
+        fn args(&self) -> Vec<&Box<dyn Expression>> {
+            vec![&self.arg]
+        }
+
+        fn to_json(&self) -> JsonValue {
+            json!({"flag": {"name": self.arg.to_json()}})
+        }
+    }
+}
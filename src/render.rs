@@ -0,0 +1,297 @@
+use crate::expression::ops::get_name;
+use crate::expression::value::Value;
+use crate::expression::Expression;
+use serde_json::Value as JsonValue;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+type TemplateFn = dyn Fn(&[String]) -> String + Send + Sync;
+
+/// Phrase for an op, filled in with the rendered args.
+#[derive(Clone)]
+pub struct Template {
+    kind: TemplateKind,
+}
+
+#[derive(Clone)]
+enum TemplateKind {
+    Pattern(String),
+    Join(String),
+    Custom(Arc<TemplateFn>),
+}
+
+impl Template {
+    /// Replaces `{0}`, `{1}`, ... with the arg at that position and `{args}` with all
+    /// args separated by commas, e.g. `"{0} is greater than {1}"`.
+    pub fn pattern<S>(pattern: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            kind: TemplateKind::Pattern(pattern.into()),
+        }
+    }
+
+    /// Joins all args with `separator`, e.g. `" and "`. Args which are joined themselves
+    /// are put in parentheses.
+    pub fn join<S>(separator: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            kind: TemplateKind::Join(separator.into()),
+        }
+    }
+
+    pub fn custom<F>(render: F) -> Self
+    where
+        F: Fn(&[String]) -> String + Send + Sync + 'static,
+    {
+        Self {
+            kind: TemplateKind::Custom(Arc::new(render)),
+        }
+    }
+
+    fn apply(&self, args: &[String]) -> String {
+        match &self.kind {
+            TemplateKind::Pattern(pattern) => {
+                let mut text = String::new();
+                let mut rest = pattern.as_str();
+
+                while let Some(start) = rest.find('{') {
+                    let end = match rest[start..].find('}') {
+                        Some(end) => start + end,
+                        None => break,
+                    };
+                    let placeholder = &rest[start + 1..end];
+                    text.push_str(&rest[..start]);
+
+                    match placeholder.parse::<usize>() {
+                        Ok(position) if position < args.len() => text.push_str(&args[position]),
+                        _ if placeholder == "args" => text.push_str(&args.join(", ")),
+                        _ => text.push_str(&rest[start..=end]),
+                    }
+
+                    rest = &rest[end + 1..];
+                }
+
+                text.push_str(rest);
+                text
+            }
+            TemplateKind::Join(separator) => args.join(separator),
+            TemplateKind::Custom(render) => render(args),
+        }
+    }
+}
+
+impl fmt::Debug for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            TemplateKind::Pattern(pattern) => write!(f, "Template::pattern({:?})", pattern),
+            TemplateKind::Join(separator) => write!(f, "Template::join({:?})", separator),
+            TemplateKind::Custom(_) => write!(f, "Template::custom(..)"),
+        }
+    }
+}
+
+/// Renders expressions as English text, such as `age is greater than 17`.
+///
+/// A template set for an op name takes precedence over the op's own
+/// `Expression::template()`, and ops with neither are rendered as `name(arg, ...)`.
+/// String literals are quoted, and args of a pattern other than literals and `get`s
+/// are put in parentheses.
+#[derive(Clone, Debug, Default)]
+pub struct Renderer {
+    templates: HashMap<String, Template>,
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_template<S>(mut self, op: S, template: Template) -> Self
+    where
+        S: Into<String>,
+    {
+        self.templates.insert(op.into(), template);
+        self
+    }
+
+    pub fn render(&self, expression: &dyn Expression) -> String {
+        if let Some(value) = expression.as_value() {
+            return render_value(value);
+        }
+
+        let template = self.template(expression);
+        let args: Vec<String> = match get_name(expression) {
+            Some(name) => vec![name],
+            None => expression
+                .args()
+                .into_iter()
+                .map(|arg| {
+                    let text = self.render(arg.as_ref());
+                    let compound = match template.as_deref().map(|template| &template.kind) {
+                        Some(TemplateKind::Join(_)) => self.is_joined(arg.as_ref()),
+                        Some(TemplateKind::Pattern(_)) => {
+                            arg.as_value().is_none() && get_name(arg.as_ref()).is_none()
+                        }
+                        _ => false,
+                    };
+
+                    if compound {
+                        format!("({})", text)
+                    } else {
+                        text
+                    }
+                })
+                .collect(),
+        };
+
+        match template {
+            Some(template) => template.apply(&args),
+            None => format!("{}({})", expression.name(), args.join(", ")),
+        }
+    }
+
+    fn template(&self, expression: &dyn Expression) -> Option<Cow<'_, Template>> {
+        match self.templates.get(expression.name()) {
+            Some(template) => Some(Cow::Borrowed(template)),
+            None => expression.template().map(Cow::Owned),
+        }
+    }
+
+    fn is_joined(&self, expression: &dyn Expression) -> bool {
+        expression.as_value().is_none()
+            && expression.args().len() > 1
+            && matches!(
+                self.template(expression)
+                    .as_deref()
+                    .map(|template| &template.kind),
+                Some(TemplateKind::Join(_))
+            )
+    }
+}
+
+fn render_value(value: &Value) -> String {
+    fn list<T: ToString>(items: &[T]) -> String {
+        let items: Vec<String> = items.iter().map(ToString::to_string).collect();
+        format!("[{}]", items.join(", "))
+    }
+
+    fn quote(content: &str) -> String {
+        JsonValue::from(content).to_string()
+    }
+
+    match value {
+        Value::Bool(content) => content.to_string(),
+        Value::BoolArray(content) => list(content),
+        Value::Int(content) => content.to_string(),
+        Value::IntArray(content) => list(content),
+        Value::Float(content) => content.to_string(),
+        Value::FloatArray(content) => list(content),
+        Value::Str(content) => quote(content),
+        Value::StrArray(content) => {
+            let items: Vec<String> = content.iter().map(|item| quote(item)).collect();
+            list(&items)
+        }
+    }
+}
+
+impl dyn Expression + '_ {
+    /// English text of the expression, rendered with the default `Renderer`.
+    pub fn to_text(&self) -> String {
+        Renderer::new().render(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::Context;
+    use crate::expression::eval_type::Type;
+    use crate::expression::ops::*;
+    use crate::expression::{args_context_dependencies, EvalResult};
+    use crate::parser::parse;
+
+    #[test]
+    fn it_renders_english_text() {
+        let expression = parse(
+            r#"{"or": [
+                {"and": [{"gt": [{"get": ["age"]}, 17]}, {"eq": [{"get": ["country"]}, "DE"]}]},
+                {"not": [{"eq": [{"get": ["roles"]}, ["admin", "dev"]]}]}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(gt(get("age"), int(17)).to_text(), "age is greater than 17");
+        assert_eq!(
+            expression.to_text(),
+            r#"(age is greater than 17 and country is "DE") or it is not the case that (roles is ["admin", "dev"])"#
+        );
+    }
+
+    #[test]
+    fn it_renders_with_custom_templates() {
+        let renderer = Renderer::new()
+            .set_template("eq", Template::pattern("{0} equals {1}"))
+            .set_template("not", Template::custom(|args| format!("NOT {}", args[0])))
+            .set_template("startsWith", Template::pattern("{0} begins with {1}"));
+        let expression = and([
+            not(eq(get("plan"), str("free"))),
+            Box::new(StartsWith {
+                args: vec![get("email"), str("admin@")],
+            }),
+        ]);
+
+        assert_eq!(
+            renderer.render(expression.as_ref()),
+            r#"NOT plan equals "free" and email begins with "admin@""#
+        );
+        assert_eq!(
+            expression.to_text(),
+            r#"it is not the case that (plan is "free") and email starts with "admin@""#
+        );
+        assert_eq!(
+            Renderer::new()
+                .set_template("eq", Template::pattern("{0} = {1}"))
+                .render(eq(gt(get("age"), int(17)), bool(true)).as_ref()),
+            "(age is greater than 17) = true"
+        );
+    }
+
+    #[derive(Clone)]
+    struct StartsWith {
+        args: Vec<Box<dyn Expression>>,
+    }
+
+    impl Expression for StartsWith {
+        fn eval(&self, context: &Context) -> EvalResult<Value> {
+            let text = self.args[0].eval_str(context)?;
+            let prefix = self.args[1].eval_str(context)?;
+            Ok(Value::Bool(text.starts_with(&prefix)))
+        }
+
+        fn eval_type(&self, _context: &Context) -> EvalResult<Type> {
+            Ok(Type::Bool)
+        }
+
+        fn context_dependencies(&self) -> Option<Vec<String>> {
+            args_context_dependencies(self)
+        }
+
+        fn name(&self) -> &str {
+            "startsWith"
+        }
+
+        fn args(&self) -> Vec<&Box<dyn Expression>> {
+            self.args.iter().collect()
+        }
+
+        fn template(&self) -> Option<Template> {
+            Some(Template::pattern("{0} starts with {1}"))
+        }
+    }
+}
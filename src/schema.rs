@@ -0,0 +1,314 @@
+use crate::context::Context;
+use crate::expression::eval_type::Type;
+use crate::expression::value::Value;
+use std::collections::BTreeMap;
+
+/// Type of a context value declared up front. `Type` cannot serve here because its array
+/// variants carry a length, which is only known once a value exists: a `Vec<i64>` field
+/// holds arrays of any length, while `Type::IntArray(2)` only matches arrays of two
+/// items. `SchemaType::of()` drops the length, so `matches()` accepts every length.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SchemaType {
+    Bool,
+    BoolArray,
+    Int,
+    IntArray,
+    Float,
+    FloatArray,
+    Str,
+    StrArray,
+}
+
+impl SchemaType {
+    pub fn of(value_type: &Type) -> Self {
+        match value_type {
+            Type::Bool => SchemaType::Bool,
+            Type::BoolArray(_) => SchemaType::BoolArray,
+            Type::Int => SchemaType::Int,
+            Type::IntArray(_) => SchemaType::IntArray,
+            Type::Float => SchemaType::Float,
+            Type::FloatArray(_) => SchemaType::FloatArray,
+            Type::Str => SchemaType::Str,
+            Type::StrArray(_) => SchemaType::StrArray,
+        }
+    }
+
+    pub fn matches(&self, value_type: &Type) -> bool {
+        SchemaType::of(value_type) == *self
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SchemaField {
+    pub schema_type: SchemaType,
+    /// Nullable fields may be missing from a context.
+    pub nullable: bool,
+}
+
+/// Names and types of the values a context is expected to hold.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Schema {
+    fields: BTreeMap<String, SchemaField>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set<S>(mut self, name: S, schema_type: SchemaType, nullable: bool) -> Self
+    where
+        S: Into<String>,
+    {
+        self.insert(name, schema_type, nullable);
+        self
+    }
+
+    pub fn insert<S>(&mut self, name: S, schema_type: SchemaType, nullable: bool)
+    where
+        S: Into<String>,
+    {
+        self.fields.insert(
+            name.into(),
+            SchemaField {
+                schema_type,
+                nullable,
+            },
+        );
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SchemaField> {
+        self.fields.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &SchemaField)> {
+        self.fields
+            .iter()
+            .map(|(name, field)| (name.as_str(), field))
+    }
+}
+
+/// Conversion of a Rust struct into a `Context`, usually implemented with
+/// `#[derive(JetContext)]`. Nested structs are flattened into dotted names.
+pub trait JetContext {
+    fn write_context(&self, prefix: &str, context: &mut Context);
+    fn write_schema(prefix: &str, nullable: bool, schema: &mut Schema);
+
+    fn to_context(&self) -> Context {
+        let mut context = Context::new();
+        self.write_context("", &mut context);
+        context
+    }
+
+    fn schema() -> Schema {
+        let mut schema = Schema::new();
+        Self::write_schema("", false, &mut schema);
+        schema
+    }
+}
+
+/// Field of a `JetContext` struct: a scalar, an array, an `Option` or a nested struct.
+pub trait ContextField {
+    fn write_field(&self, name: &str, context: &mut Context);
+    fn write_field_schema(name: &str, nullable: bool, schema: &mut Schema);
+}
+
+impl<T> ContextField for T
+where
+    T: JetContext,
+{
+    fn write_field(&self, name: &str, context: &mut Context) {
+        self.write_context(name, context)
+    }
+
+    fn write_field_schema(name: &str, nullable: bool, schema: &mut Schema) {
+        T::write_schema(name, nullable, schema)
+    }
+}
+
+impl<T> ContextField for Option<T>
+where
+    T: ContextField,
+{
+    fn write_field(&self, name: &str, context: &mut Context) {
+        if let Some(content) = self {
+            content.write_field(name, context)
+        }
+    }
+
+    fn write_field_schema(name: &str, _nullable: bool, schema: &mut Schema) {
+        T::write_field_schema(name, true, schema)
+    }
+}
+
+macro_rules! impl_context_field {
+    ($schema_type:ident, $($field_type:ty => $convert:expr),+ $(,)?) => {
+        $(
+            impl ContextField for $field_type {
+                fn write_field(&self, name: &str, context: &mut Context) {
+                    let convert: fn(&$field_type) -> Value = $convert;
+                    context.insert(name, convert(self));
+                }
+
+                fn write_field_schema(name: &str, nullable: bool, schema: &mut Schema) {
+                    schema.insert(name, SchemaType::$schema_type, nullable)
+                }
+            }
+        )+
+    };
+}
+
+impl_context_field!(Bool, bool => |content| Value::Bool(*content));
+impl_context_field!(BoolArray, Vec<bool> => |content| Value::BoolArray(content.clone()));
+impl_context_field!(
+    Int,
+    i64 => |content| Value::Int(*content),
+    i32 => |content| Value::Int(*content as i64),
+    i16 => |content| Value::Int(*content as i64),
+    i8 => |content| Value::Int(*content as i64),
+    u32 => |content| Value::Int(*content as i64),
+    u16 => |content| Value::Int(*content as i64),
+    u8 => |content| Value::Int(*content as i64),
+);
+impl_context_field!(
+    IntArray,
+    Vec<i64> => |content| Value::IntArray(content.clone()),
+    Vec<i32> => |content| Value::IntArray(content.iter().map(|item| *item as i64).collect()),
+);
+impl_context_field!(
+    Float,
+    f64 => |content| Value::Float(*content),
+    f32 => |content| Value::Float(*content as f64),
+);
+impl_context_field!(FloatArray, Vec<f64> => |content| Value::FloatArray(content.clone()));
+impl_context_field!(
+    Str,
+    String => |content| Value::Str(content.clone()),
+    &str => |content| Value::Str(content.to_string()),
+);
+impl_context_field!(StrArray, Vec<String> => |content| Value::StrArray(content.clone()));
+
+#[doc(hidden)]
+pub fn context_path(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", prefix, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Address {
+        country: String,
+        zip: Option<String>,
+    }
+
+    impl JetContext for Address {
+        fn write_context(&self, prefix: &str, context: &mut Context) {
+            self.country
+                .write_field(&context_path(prefix, "country"), context);
+            self.zip.write_field(&context_path(prefix, "zip"), context);
+        }
+
+        fn write_schema(prefix: &str, nullable: bool, schema: &mut Schema) {
+            String::write_field_schema(&context_path(prefix, "country"), nullable, schema);
+            Option::<String>::write_field_schema(&context_path(prefix, "zip"), nullable, schema);
+        }
+    }
+
+    #[test]
+    fn it_writes_fields_and_schemas_by_hand() {
+        let address = Address {
+            country: "DE".to_string(),
+            zip: None,
+        };
+        let context = address.to_context();
+
+        assert_eq!(context.get("country"), Some(&Value::Str("DE".to_string())));
+        assert!(!context.contains_key("zip"));
+        assert_eq!(
+            Address::schema(),
+            Schema::new()
+                .set("country", SchemaType::Str, false)
+                .set("zip", SchemaType::Str, true)
+        );
+    }
+
+    #[test]
+    fn it_matches_arrays_of_any_length() {
+        assert!(SchemaType::IntArray.matches(&Type::IntArray(0)));
+        assert!(SchemaType::IntArray.matches(&Type::IntArray(3)));
+        assert!(!SchemaType::IntArray.matches(&Type::FloatArray(3)));
+        assert_eq!(SchemaType::of(&Type::Str), SchemaType::Str);
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod derive_tests {
+    use super::*;
+    use crate::expression::ops::*;
+    use crate::JetContext;
+
+    #[derive(JetContext)]
+    struct Address {
+        country: String,
+        zip: Option<String>,
+    }
+
+    #[derive(JetContext)]
+    struct Request {
+        #[jet(rename = "userId")]
+        user_id: i64,
+        score: f64,
+        roles: Vec<String>,
+        address: Address,
+        billing: Option<Address>,
+        beta: Option<bool>,
+    }
+
+    fn request() -> Request {
+        Request {
+            user_id: 1,
+            score: 0.5,
+            roles: vec!["admin".to_string()],
+            address: Address {
+                country: "DE".to_string(),
+                zip: None,
+            },
+            billing: None,
+            beta: Some(true),
+        }
+    }
+
+    #[test]
+    fn it_converts_structs_into_contexts() {
+        let context = request().to_context();
+
+        assert_eq!(context.get("userId"), Some(&Value::Int(1)));
+        assert_eq!(context.get("address.country"), Some(&str_val("DE")));
+        assert_eq!(context.get("beta"), Some(&Value::Bool(true)));
+        assert!(!context.contains_key("address.zip"));
+        assert!(!context.contains_key("billing.country"));
+        assert!(eq(get("roles"), str_array(["admin"]))
+            .eval_bool(&context)
+            .unwrap());
+    }
+
+    #[test]
+    fn it_derives_schemas() {
+        let schema = Schema::new()
+            .set("userId", SchemaType::Int, false)
+            .set("score", SchemaType::Float, false)
+            .set("roles", SchemaType::StrArray, false)
+            .set("address.country", SchemaType::Str, false)
+            .set("address.zip", SchemaType::Str, true)
+            .set("billing.country", SchemaType::Str, true)
+            .set("billing.zip", SchemaType::Str, true)
+            .set("beta", SchemaType::Bool, true);
+
+        assert_eq!(Request::schema(), schema);
+    }
+}